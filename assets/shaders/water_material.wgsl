@@ -1,27 +1,41 @@
 // A shader that creates water ripples by overlaying 4 normal maps on top of one
 // another.
 //
-// This is used in the `ssr` example. It only supports deferred rendering.
+// Adapted from the `ssr` example for the map, which lies in the XY plane with Z up.
+// The ripples are sampled in world space so they run seamlessly across tiles. Both
+// deferred and forward rendering are supported.
 
+#import bevy_pbr::pbr_fragment::pbr_input_from_standard_material
+#import bevy_render::globals::Globals
+
+#ifdef PREPASS_PIPELINE
 #import bevy_pbr::{
     pbr_deferred_functions::deferred_output,
-    pbr_fragment::pbr_input_from_standard_material,
     prepass_io::{VertexOutput, FragmentOutput},
 }
-#import bevy_render::globals::Globals
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    mesh_view_bindings::globals,
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
 
 // Parameters to the water shader.
 struct WaterSettings {
-    // How much to displace each octave each frame, in the u and v directions.
+    // How much to displace each octave each second, in the x and y directions.
     // Two octaves are packed into each `vec4`.
     octave_vectors: array<vec4<f32>, 2>,
-    // How wide the waves are in each octave.
+    // How wide the waves are in each octave, in repetitions per world unit.
     octave_scales: vec4<f32>,
     // How high the waves are in each octave.
     octave_strengths: vec4<f32>,
 }
 
+#ifdef PREPASS_PIPELINE
+// The prepass view bindings have no import for the globals.
 @group(0) @binding(1) var<uniform> globals: Globals;
+#endif
 
 @group(2) @binding(100) var water_normals_texture: texture_2d<f32>;
 @group(2) @binding(101) var water_normals_sampler: sampler;
@@ -29,9 +43,9 @@ struct WaterSettings {
 
 // Samples a single octave of noise and returns the resulting normal.
 fn sample_noise_octave(uv: vec2<f32>, strength: f32) -> vec3<f32> {
-    let N = textureSample(water_normals_texture, water_normals_sampler, uv).rbg * 2.0 - 1.0;
+    let N = textureSample(water_normals_texture, water_normals_sampler, uv).rgb * 2.0 - 1.0;
     // This isn't slerp, but it's good enough.
-    return normalize(mix(vec3(0.0, 1.0, 0.0), N, strength)); 
+    return normalize(mix(vec3(0.0, 0.0, 1.0), N, strength));
 }
 
 // Samples all four octaves of noise and returns the resulting normal.
@@ -52,8 +66,17 @@ fn sample_noise(uv: vec2<f32>, time: f32) -> vec3<f32> {
 fn fragment(in: VertexOutput, @builtin(front_facing) is_front: bool) -> FragmentOutput {
     // Create the PBR input.
     var pbr_input = pbr_input_from_standard_material(in, is_front);
-    // Bump the normal.
-    pbr_input.N = sample_noise(in.uv, globals.time);
+    // Bump the normal of the top faces only, the sides of the tiles keep theirs.
+    if (pbr_input.world_normal.z > 0.9) {
+        pbr_input.N = sample_noise(in.world_position.xy, globals.time);
+    }
+#ifdef PREPASS_PIPELINE
     // Send the rest to the deferred shader.
     return deferred_output(in, pbr_input);
+#else
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+    return out;
+#endif
 }
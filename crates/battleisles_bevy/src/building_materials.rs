@@ -0,0 +1,34 @@
+use battleisles_domain::player::PlayerId;
+use bevy::prelude::*;
+use bevy_color::palettes::basic::*;
+use std::collections::HashMap;
+
+// Buildings are colored by owner so captures are visible at a glance
+#[derive(Resource, Default)]
+pub struct BuildingMaterials {
+    cache: HashMap<Option<PlayerId>, Handle<StandardMaterial>>,
+}
+
+impl BuildingMaterials {
+    pub fn get_or_create(
+        &mut self,
+        owner: Option<PlayerId>,
+        materials: &mut Assets<StandardMaterial>,
+    ) -> Handle<StandardMaterial> {
+        self.cache
+            .entry(owner)
+            .or_insert_with(|| materials.add(StandardMaterial::from_color(owner_color(owner))))
+            .clone()
+    }
+}
+
+pub fn owner_color(owner: Option<PlayerId>) -> Srgba {
+    match owner.map(|id| id.0) {
+        None => SILVER,
+        Some(1) => RED,
+        Some(2) => YELLOW,
+        Some(3) => FUCHSIA,
+        Some(4) => PURPLE,
+        Some(_) => MAROON,
+    }
+}
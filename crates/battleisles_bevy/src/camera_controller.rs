@@ -0,0 +1,227 @@
+use crate::map_model::MapModel;
+use bevy::input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseScrollUnit};
+use bevy::prelude::*;
+use bevy::render::camera::Projection;
+use bevy_egui::EguiContexts;
+
+// Room left around the map when it is fitted to the view
+const FIT_MARGIN: f32 = 1.1;
+// How much further out than the fitted view the camera can be zoomed
+const MAX_ZOOM_OUT: f32 = 2.0;
+// Touchpads scroll in pixels, mouse wheels in lines
+const PIXELS_PER_LINE: f32 = 50.0;
+
+pub struct CameraControllerPlugin;
+
+impl Plugin for CameraControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FitMapToView>().add_systems(
+            Update,
+            (fit_map_to_view, zoom_camera, pan_camera, clamp_camera).chain(),
+        );
+    }
+}
+
+// Frames the whole map. This also happens whenever a new map model is created and when
+// Home is pressed.
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct FitMapToView;
+
+// Lets the player move the orthographic, top-down camera it is attached to. Scales are the
+// orthographic projection's, i.e. world units per logical pixel.
+#[derive(Component, Clone, Debug)]
+pub struct CameraController {
+    // Relative change of the scale per mouse wheel line
+    pub zoom_step: f32,
+    // Closest zoom, the farthest depends on the size of the map
+    pub min_scale: f32,
+    // Speed of keyboard and edge scrolling in pixels per second
+    pub pan_speed: f32,
+    // Width in pixels of the window border that scrolls the view, 0 turns edge scrolling off
+    pub edge_margin: f32,
+}
+
+impl Default for CameraController {
+    fn default() -> Self {
+        CameraController {
+            zoom_step: 0.1,
+            min_scale: 0.005,
+            pan_speed: 800.0,
+            edge_margin: 8.0,
+        }
+    }
+}
+
+// Scale at which `bounds` just fits into `viewport`
+fn fit_scale(bounds: Rect, viewport: Vec2) -> f32 {
+    (bounds.size() * FIT_MARGIN / viewport.max(Vec2::ONE)).max_element()
+}
+
+fn fit_map_to_view(
+    mut events: EventReader<FitMapToView>,
+    mut contexts: EguiContexts,
+    keys: Res<ButtonInput<KeyCode>>,
+    map_model: Option<Res<MapModel>>,
+    mut cameras: Query<(&Camera, &mut Transform, &mut Projection), With<CameraController>>,
+    // The viewport size is unknown until the camera has been rendered once, so a fit that
+    // cannot be done yet is remembered
+    mut pending: Local<bool>,
+) {
+    let home = keys.just_pressed(KeyCode::Home) && !contexts.ctx_mut().wants_keyboard_input();
+    *pending |= events.read().count() > 0 || home;
+    let Some(map_model) = map_model else {
+        return;
+    };
+    *pending |= map_model.is_added();
+    if !*pending {
+        return;
+    }
+
+    let bounds = map_model.bounds();
+    for (camera, mut transform, mut projection) in cameras.iter_mut() {
+        let Some(viewport) = camera.logical_viewport_size() else {
+            return;
+        };
+        let Projection::Orthographic(orthographic) = projection.as_mut() else {
+            continue;
+        };
+        orthographic.scale = fit_scale(bounds, viewport);
+        transform.translation = bounds.center().extend(transform.translation.z);
+    }
+    *pending = false;
+}
+
+// Zooms towards the point under the cursor, which stays where it is on the screen
+fn zoom_camera(
+    mut contexts: EguiContexts,
+    scroll: Res<AccumulatedMouseScroll>,
+    windows: Query<&Window>,
+    map_model: Option<Res<MapModel>>,
+    mut cameras: Query<(
+        &Camera,
+        &GlobalTransform,
+        &CameraController,
+        &mut Transform,
+        &mut Projection,
+    )>,
+) {
+    if scroll.delta.y == 0.0 || contexts.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let lines = match scroll.unit {
+        MouseScrollUnit::Line => scroll.delta.y,
+        MouseScrollUnit::Pixel => scroll.delta.y / PIXELS_PER_LINE,
+    };
+    let cursor = windows
+        .single()
+        .ok()
+        .and_then(|window| window.cursor_position());
+    let bounds = map_model
+        .map(|map_model| map_model.bounds())
+        .unwrap_or_default();
+
+    for (camera, camera_transform, controller, mut transform, mut projection) in cameras.iter_mut()
+    {
+        let Projection::Orthographic(orthographic) = projection.as_mut() else {
+            continue;
+        };
+        let max_scale = camera
+            .logical_viewport_size()
+            .map(|viewport| fit_scale(bounds, viewport) * MAX_ZOOM_OUT)
+            .unwrap_or(orthographic.scale)
+            .max(controller.min_scale);
+        let scale = (orthographic.scale * (1.0 - controller.zoom_step).powf(lines))
+            .clamp(controller.min_scale, max_scale);
+
+        // The camera looks straight down, so the ray's origin is the point under the cursor
+        let ray = cursor.and_then(|cursor| camera.viewport_to_world(camera_transform, cursor).ok());
+        if let Some(ray) = ray {
+            let anchor = ray.origin.truncate();
+            let center = transform.translation.truncate();
+            let center = anchor + (center - anchor) * scale / orthographic.scale;
+            transform.translation = center.extend(transform.translation.z);
+        }
+        orthographic.scale = scale;
+    }
+}
+
+// Middle mouse drag, WASD or the arrow keys, and moving the cursor to the window border
+fn pan_camera(
+    mut contexts: EguiContexts,
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    motion: Res<AccumulatedMouseMotion>,
+    windows: Query<&Window>,
+    mut cameras: Query<(&CameraController, &mut Transform, &Projection)>,
+) {
+    let ctx = contexts.ctx_mut();
+    // On the screen in pixels with y pointing down
+    let drag = if mouse.pressed(MouseButton::Middle) {
+        -motion.delta
+    } else {
+        Vec2::ZERO
+    };
+
+    let mut direction = Vec2::ZERO;
+    if !ctx.wants_keyboard_input() {
+        for (pressed, step) in [
+            ([KeyCode::KeyW, KeyCode::ArrowUp], Vec2::NEG_Y),
+            ([KeyCode::KeyS, KeyCode::ArrowDown], Vec2::Y),
+            ([KeyCode::KeyA, KeyCode::ArrowLeft], Vec2::NEG_X),
+            ([KeyCode::KeyD, KeyCode::ArrowRight], Vec2::X),
+        ] {
+            if keys.any_pressed(pressed) {
+                direction += step;
+            }
+        }
+    }
+    let edge = windows
+        .single()
+        .ok()
+        .filter(|window| window.focused && !ctx.is_pointer_over_area())
+        .and_then(|window| Some((window.cursor_position()?, window.size())));
+
+    for (controller, mut transform, projection) in cameras.iter_mut() {
+        let Projection::Orthographic(orthographic) = projection else {
+            continue;
+        };
+        let mut direction = direction;
+        if let Some((cursor, size)) = edge.filter(|_| controller.edge_margin > 0.0) {
+            let margin = controller.edge_margin;
+            if cursor.x <= margin {
+                direction.x -= 1.0;
+            } else if cursor.x >= size.x - margin {
+                direction.x += 1.0;
+            }
+            if cursor.y <= margin {
+                direction.y -= 1.0;
+            } else if cursor.y >= size.y - margin {
+                direction.y += 1.0;
+            }
+        }
+        let pixels =
+            drag + direction.normalize_or_zero() * controller.pan_speed * time.delta_secs();
+        if pixels != Vec2::ZERO {
+            transform.translation += Vec3::new(pixels.x, -pixels.y, 0.0) * orthographic.scale;
+        }
+    }
+}
+
+// Keeps the centre of the view over the map
+fn clamp_camera(
+    map_model: Option<Res<MapModel>>,
+    mut cameras: Query<&mut Transform, With<CameraController>>,
+) {
+    let Some(map_model) = map_model else {
+        return;
+    };
+    let bounds = map_model.bounds();
+    for mut transform in cameras.iter_mut() {
+        let center = transform.translation.truncate();
+        let clamped = center.clamp(bounds.min, bounds.max);
+        if clamped != center {
+            transform.translation = clamped.extend(transform.translation.z);
+        }
+    }
+}
@@ -1,3 +1,8 @@
-mod map_model;
+pub mod map_model;
 pub mod map_model_plugin;
+pub mod building_materials;
+pub mod camera_controller;
 mod terrain_materials;
+mod terrain_meshes;
+pub mod terrain_theme;
+pub mod water_material;
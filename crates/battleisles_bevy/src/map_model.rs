@@ -1,7 +1,16 @@
+use crate::building_materials::BuildingMaterials;
+use crate::camera_controller::CameraController;
 use crate::terrain_materials::TerrainMaterials;
-use battleisles_domain::map::Map;
+use crate::terrain_meshes::{terrain_height, TerrainMeshes};
+use crate::terrain_theme::TerrainTheme;
+use crate::water_material::{WaterMaterial, WaterMaterials};
+use battleisles_domain::building::{Building, BuildingKind};
+use battleisles_domain::map::{Hex, Map, Terrain};
+use battleisles_domain::player::PlayerId;
+use battleisles_domain::visibility::TileVisibility;
 use bevy::prelude::*;
 use bevy::render::camera::{OrthographicProjection, Projection};
+use std::collections::HashMap;
 
 #[derive(Component, Clone, Copy)]
 pub struct TileIndex(pub usize);
@@ -9,11 +18,22 @@ pub struct TileIndex(pub usize);
 #[derive(Resource)]
 pub struct MapModel {
     map: Map,
-    hex_mesh: Handle<Mesh>,
+    terrain_meshes: TerrainMeshes,
     terrain_materials: TerrainMaterials,
     light: Entity,
     camera: Entity,
     tile_entities: Vec<Entity>,
+    building_meshes: HashMap<BuildingKind, Handle<Mesh>>,
+    building_materials: BuildingMaterials,
+    // Children of the tile entities, one per tile that has a building
+    building_entities: Vec<Option<Entity>>,
+    // Fog of war as seen by `fog_viewer`, everything is visible while there is no viewer
+    fog_viewer: Option<PlayerId>,
+    visibility: Vec<TileVisibility>,
+    // Offset subtracted from (Y-flipped) domain positions to center the map on the origin
+    center: Vec2,
+    // Area covered by the tiles in the centered space
+    bounds: Rect,
 }
 
 impl MapModel {
@@ -24,43 +44,44 @@ impl MapModel {
         materials: &mut ResMut<Assets<StandardMaterial>>,
     ) -> Result<Self, bool> {
         let mut terrain_materials = TerrainMaterials::default();
-        let hex_mesh = meshes.add(Extrusion::new(RegularPolygon::new(map.hex_size(), 6), 0.1));
+        let terrain_meshes = TerrainMeshes::new(map.hex_size(), map.orientation(), meshes);
 
-        // Compute bounds to center the map and flip Y so row 0 is at the top
-        let mut min_x = f32::INFINITY;
-        let mut max_x = f32::NEG_INFINITY;
-        let mut min_y = f32::INFINITY;
-        let mut max_y = f32::NEG_INFINITY;
-
-        for tile in &map.tiles {
-            let (x_raw, y_raw) = map.tile_to_world_pos(tile);
-            let x = x_raw;
-            let y = -y_raw; // flip Y so r=0 is at the top
-            if x < min_x { min_x = x; }
-            if x > max_x { max_x = x; }
-            if y < min_y { min_y = y; }
-            if y > max_y { max_y = y; }
-        }
+        // Center the map and flip Y so row 0 is at the top
+        let (center, bounds) = match map.world_bounds() {
+            Some(world_bounds) => {
+                let (x, y) = world_bounds.center();
+                let half_size = Vec2::from(world_bounds.size()) * 0.5;
+                let bounds = Rect::from_center_half_size(Vec2::ZERO, half_size);
+                ((x, -y), bounds.inflate(map.hex_size()))
+            }
+            None => ((0.0, 0.0), Rect::default()),
+        };
 
-        let center = ((min_x + max_x) * 0.5, (min_y + max_y) * 0.5);
+        let building_meshes = BuildingKind::ALL
+            .into_iter()
+            .map(|kind| (kind, meshes.add(building_mesh(kind, map.hex_size()))))
+            .collect();
 
-        let mut tile_entities = Vec::with_capacity(map.tiles.len());
-        for (i, tile) in map.tiles.iter().enumerate() {
+        let mut tile_entities = Vec::with_capacity(map.tiles().len());
+        for (i, tile) in map.tiles().iter().enumerate() {
             let (x_raw, y_raw) = map.tile_to_world_pos(tile);
             let x = x_raw - center.0;
             let y = -y_raw - center.1; // flip Y and center
-            let material = terrain_materials.get_or_create(tile.terrain, materials.as_mut());
-            let entity = commands
-                .spawn((
-                    Mesh3d(hex_mesh.clone()),
-                    MeshMaterial3d(material.clone()),
-                    Transform {
-                        translation: Vec3::new(x, y, 0.0),
-                        ..default()
-                    },
-                    TileIndex(i),
-                ))
-                .id();
+            let material = terrain_materials.get_or_create(
+                tile.terrain,
+                TileVisibility::Visible,
+                materials.as_mut(),
+            );
+            let mut entity = commands.spawn((
+                Mesh3d(terrain_meshes.get(tile.terrain)),
+                Transform {
+                    translation: Vec3::new(x, y, 0.0),
+                    ..default()
+                },
+                TileIndex(i),
+            ));
+            insert_terrain_material(&mut entity, tile.terrain, TileVisibility::Visible, material);
+            let entity = entity.id();
             tile_entities.push(entity);
         }
 
@@ -80,79 +101,288 @@ impl MapModel {
         let camera_id = commands
             .spawn((
                 Camera3d { ..default() },
+                // Scaled to the window; the camera controller fits the map into it
                 Projection::Orthographic(OrthographicProjection {
                     scale: 0.1,
-                    scaling_mode: bevy::render::camera::ScalingMode::Fixed {
-                        width: 800.0,
-                        height: 600.0,
-                    },
                     near: -1000.0,
                     far: 1000.0,
                     ..OrthographicProjection::default_3d()
                 }),
                 Transform::from_xyz(0.0, 0.0, 1000.0).looking_at(Vec3::ZERO, Vec3::Y),
                 GlobalTransform::default(),
+                CameraController::default(),
             ))
             .id();
 
-        Ok(MapModel {
+        let mut map_model = MapModel {
             map,
-            hex_mesh,
+            terrain_meshes,
             terrain_materials,
             light: light_entity,
             camera: camera_id,
+            building_entities: vec![None; tile_entities.len()],
+            fog_viewer: None,
+            visibility: vec![TileVisibility::Visible; tile_entities.len()],
             tile_entities,
+            building_meshes,
+            building_materials: BuildingMaterials::default(),
+            center: Vec2::new(center.0, center.1),
+            bounds,
+        };
+        for index in 0..map_model.tile_entities.len() {
+            map_model.spawn_building(index, materials, commands);
+        }
+        Ok(map_model)
+    }
+
+    pub fn map(&self) -> &Map {
+        &self.map
+    }
+
+    pub fn camera(&self) -> Entity {
+        self.camera
+    }
+
+    // Top-level entities spawned for the map; buildings are despawned with their tiles
+    pub(crate) fn owned_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.tile_entities
+            .iter()
+            .copied()
+            .chain([self.light, self.camera])
+    }
+
+    // Area covered by the tiles in the centered, Y-flipped space the tiles are spawned in
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    // Centre of the tile at `hex` in the centered, Y-flipped space the tiles are spawned in
+    pub fn tile_world_pos(&self, hex: Hex) -> Option<Vec2> {
+        self.map.contains(hex).then(|| self.hex_world_pos(hex))
+    }
+
+    // Like tile_world_pos for any hex, on the map or not
+    pub fn hex_world_pos(&self, hex: Hex) -> Vec2 {
+        let (x, y) = self.map.hex_to_world_pos(hex);
+        Vec2::new(x - self.center.x, -y - self.center.y)
+    }
+
+    // Centre of the top face of the tile at `hex`, where buildings and units stand
+    pub fn tile_surface(&self, hex: Hex) -> Option<Vec3> {
+        let pos = self.tile_world_pos(hex)?;
+        let terrain = self.map.tile_at(hex)?.terrain;
+        Some(pos.extend(self.tile_top(terrain)))
+    }
+
+    // The point in the XY plane under the tile surface the ray hits first, to be used as
+    // `world_pos` in the map model events. Surfaces are tested from the highest terrain down,
+    // so raised tiles are picked correctly even when the camera does not look straight down.
+    pub fn pick(&self, ray: Ray3d) -> Option<Vec2> {
+        let mut heights = Terrain::ALL.map(|terrain| self.tile_top(terrain));
+        heights.sort_by(|a, b| b.total_cmp(a));
+        heights.into_iter().find_map(|height| {
+            let distance = ray.intersect_plane(Vec3::Z * height, InfinitePlane3d::new(Vec3::Z))?;
+            let point = ray.get_point(distance).truncate();
+            let (index, _) = self.tile_entity_at(point)?;
+            let top = self.tile_top(self.map.tiles()[index].terrain);
+            (top >= height).then_some(point)
         })
     }
 
-    fn center_from_bounds(&self) -> (f32, f32) {
-        let mut min_x = f32::INFINITY;
-        let mut max_x = f32::NEG_INFINITY;
-        let mut min_y = f32::INFINITY;
-        let mut max_y = f32::NEG_INFINITY;
-        for t in &self.map.tiles {
-            let (x, y_raw) = self.map.tile_to_world_pos(t);
-            let y = -y_raw;
-            if x < min_x { min_x = x; }
-            if x > max_x { max_x = x; }
-            if y < min_y { min_y = y; }
-            if y > max_y { max_y = y; }
-        }
-        ((min_x + max_x) * 0.5, (min_y + max_y) * 0.5)
+    // Height of the top face of `terrain` tiles
+    pub fn tile_top(&self, terrain: Terrain) -> f32 {
+        terrain_height(terrain) * self.map.hex_size()
+    }
+
+    pub fn fog_viewer(&self) -> Option<PlayerId> {
+        self.fog_viewer
     }
 
-    fn tile_world_centered(&self, index: usize) -> Vec2 {
-        let (cx, cy) = self.center_from_bounds();
-        let (x_raw, y_raw) = self.map.tile_to_world_pos(&self.map.tiles[index]);
-        Vec2::new(x_raw - cx, -y_raw - cy)
+    pub fn tile_visibility(&self, hex: Hex) -> TileVisibility {
+        self.map
+            .tile_index(hex)
+            .map(|index| self.visibility[index])
+            .unwrap_or(TileVisibility::Unexplored)
     }
 
-    pub(crate) fn find_nearest_tile_entity(&self, world_pos: Vec2) -> Option<(usize, Entity)> {
-        let mut best_i = None;
-        let mut best_d2 = f32::INFINITY;
-        for i in 0..self.map.tiles.len() {
-            let p = self.tile_world_centered(i);
-            let d2 = p.distance_squared(world_pos);
-            if d2 < best_d2 {
-                best_d2 = d2;
-                best_i = Some(i);
+    // Shows the map as `viewer` knows it; `tiles` is in the order of `map().tiles()`.
+    // With no viewer the whole map is revealed and `tiles` is ignored.
+    pub fn set_fog_of_war(
+        &mut self,
+        viewer: Option<PlayerId>,
+        tiles: &[TileVisibility],
+        materials: &mut ResMut<Assets<StandardMaterial>>,
+        commands: &mut Commands,
+    ) {
+        self.fog_viewer = viewer;
+        for index in 0..self.visibility.len() {
+            let visibility = match viewer {
+                Some(_) => tiles.get(index).copied().unwrap_or(TileVisibility::Unexplored),
+                None => TileVisibility::Visible,
+            };
+            if self.visibility[index] == visibility {
+                continue;
+            }
+            self.visibility[index] = visibility;
+            let terrain = self.map.tiles()[index].terrain;
+            let handle = self.terrain_materials.get_or_create(terrain, visibility, materials);
+            let mut entity = commands.entity(self.tile_entities[index]);
+            insert_terrain_material(&mut entity, terrain, visibility, handle);
+            if let Some(entity) = self.building_entities[index] {
+                commands.entity(entity).insert(building_visibility(visibility));
             }
         }
-        best_i.map(|i| (i, self.tile_entities[i]))
     }
 
-    pub(crate) fn set_tile_terrain(
+    pub fn set_terrain_theme(
+        &mut self,
+        theme: &TerrainTheme,
+        asset_server: &AssetServer,
+        materials: &mut Assets<StandardMaterial>,
+    ) {
+        self.terrain_materials.set_theme(theme, asset_server, materials);
+    }
+
+    // The map tile at `world_pos`, which is in the centered, Y-flipped space the tiles are
+    // spawned in (see pick)
+    pub fn hex_at(&self, world_pos: Vec2) -> Option<Hex> {
+        let x = world_pos.x + self.center.x;
+        let y = -(world_pos.y + self.center.y);
+        let hex = self.map.world_pos_to_hex(x, y);
+        self.map.contains(hex).then_some(hex)
+    }
+
+    // `world_pos` is in the centered, Y-flipped space the tiles are spawned in
+    pub(crate) fn tile_entity_at(&self, world_pos: Vec2) -> Option<(usize, Entity)> {
+        let index = self.map.tile_index(self.hex_at(world_pos)?)?;
+        Some((index, self.tile_entities[index]))
+    }
+
+    // Returns the terrain the tile had before, or None if the index is out of range
+    pub fn set_tile_terrain(
         &mut self,
         index: usize,
         terrain: battleisles_domain::map::Terrain,
         materials: &mut ResMut<Assets<StandardMaterial>>,
         commands: &mut Commands,
+    ) -> Option<battleisles_domain::map::Terrain> {
+        let tile = self.map.tiles_mut().get_mut(index)?;
+        let previous = std::mem::replace(&mut tile.terrain, terrain);
+        let visibility = self.visibility[index];
+        let handle = self
+            .terrain_materials
+            .get_or_create(terrain, visibility, materials);
+        let mut entity = commands.entity(self.tile_entities[index]);
+        entity.insert(Mesh3d(self.terrain_meshes.get(terrain)));
+        insert_terrain_material(&mut entity, terrain, visibility, handle);
+        if let Some(building) = self.building_entities[index] {
+            commands.entity(building).insert(self.building_transform(index));
+        }
+        Some(previous)
+    }
+
+    // Returns the building the tile had before, or None if the index is out of range
+    pub fn set_tile_building(
+        &mut self,
+        index: usize,
+        building: Option<Building>,
+        materials: &mut ResMut<Assets<StandardMaterial>>,
+        commands: &mut Commands,
+    ) -> Option<Option<Building>> {
+        let tile = self.map.tiles_mut().get_mut(index)?;
+        let previous = std::mem::replace(&mut tile.building, building);
+        if let Some(entity) = self.building_entities[index].take() {
+            commands.entity(entity).despawn();
+        }
+        self.spawn_building(index, materials, commands);
+        Some(previous)
+    }
+
+    fn spawn_building(
+        &mut self,
+        index: usize,
+        materials: &mut Assets<StandardMaterial>,
+        commands: &mut Commands,
     ) {
-        if let Some(tile) = self.map.tiles.get_mut(index) {
-            tile.terrain = terrain;
-            let entity = self.tile_entities[index];
-            let handle = self.terrain_materials.get_or_create(terrain, materials);
-            commands.entity(entity).insert(MeshMaterial3d(handle));
+        let Some(building) = self.map.tiles()[index].building else {
+            return;
+        };
+        let mesh = self.building_meshes[&building.kind].clone();
+        let material = self.building_materials.get_or_create(building.owner, materials);
+        let entity = commands
+            .spawn((
+                Mesh3d(mesh),
+                MeshMaterial3d(material),
+                self.building_transform(index),
+                building_visibility(self.visibility[index]),
+                ChildOf(self.tile_entities[index]),
+            ))
+            .id();
+        self.building_entities[index] = Some(entity);
+    }
+
+    // Relative to the tile entity. The building meshes are centred, so they are lifted
+    // onto the tile's top face.
+    fn building_transform(&self, index: usize) -> Transform {
+        let tile = &self.map.tiles()[index];
+        let height = tile.building.map(|b| building_height(b.kind)).unwrap_or_default();
+        Transform::from_xyz(0.0, 0.0, self.tile_top(tile.terrain) + height * 0.5)
+    }
+}
+
+// Water in sight is animated with the water material, once the command is applied and as
+// long as the `WaterMaterials` resource exists. Everything else, including water in the
+// fog, uses the plain terrain material `handle`.
+fn insert_terrain_material(
+    entity: &mut EntityCommands,
+    terrain: Terrain,
+    visibility: TileVisibility,
+    handle: Handle<StandardMaterial>,
+) {
+    entity
+        .remove::<MeshMaterial3d<WaterMaterial>>()
+        .insert(MeshMaterial3d(handle));
+    if visibility != TileVisibility::Visible {
+        return;
+    }
+    entity.queue(move |mut entity: EntityWorldMut| {
+        let water = entity
+            .world()
+            .get_resource::<WaterMaterials>()
+            .and_then(|water| water.get(terrain));
+        if let Some(water) = water {
+            entity
+                .remove::<MeshMaterial3d<StandardMaterial>>()
+                .insert(MeshMaterial3d(water));
         }
+    });
+}
+
+fn building_visibility(visibility: TileVisibility) -> Visibility {
+    match visibility {
+        TileVisibility::Unexplored => Visibility::Hidden,
+        _ => Visibility::Inherited,
+    }
+}
+
+// Simple blocks with a distinct silhouette per kind, sized relative to the hex
+fn building_mesh(kind: BuildingKind, hex_size: f32) -> Mesh {
+    let (width, depth) = match kind {
+        BuildingKind::Headquarters => (0.9, 0.9),
+        BuildingKind::Factory => (1.0, 0.6),
+        BuildingKind::Depot => (0.5, 0.5),
+        BuildingKind::Airfield => (1.3, 0.3),
+        BuildingKind::Harbour => (0.3, 1.1),
+    };
+    Cuboid::new(width * hex_size, depth * hex_size, building_height(kind)).into()
+}
+
+fn building_height(kind: BuildingKind) -> f32 {
+    match kind {
+        BuildingKind::Headquarters => 0.8,
+        BuildingKind::Factory => 0.5,
+        BuildingKind::Depot => 0.4,
+        BuildingKind::Airfield => 0.05,
+        BuildingKind::Harbour => 0.15,
     }
 }
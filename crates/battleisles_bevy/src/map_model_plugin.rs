@@ -1,29 +1,104 @@
-use battleisles_domain::map::Map;
+use battleisles_domain::building::Building;
+use battleisles_domain::map::{Hex, Map, Symmetry};
+use battleisles_domain::player::PlayerId;
+use battleisles_domain::visibility::TileVisibility;
 use bevy::prelude::*;
+use bevy::render::RenderApp;
 
 pub struct MapModelPlugin;
 use crate::map_model::MapModel;
+use crate::terrain_theme::{CurrentTerrainTheme, TerrainTheme, TerrainThemeLoader};
+use crate::water_material::{WaterMaterial, WaterMaterials};
 
 impl Plugin for MapModelPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<ApplyTerrainAt>()
-            .add_systems(Update, handle_apply_terrain_at);
+        // Building the map only needs meshes and standard materials, so the plugin also works
+        // headless with MinimalPlugins, e.g. in tests
+        init_assets::<Mesh>(app);
+        init_assets::<StandardMaterial>(app);
+        app.init_resource::<TerrainSymmetry>()
+            .add_event::<ApplyTerrainAt>()
+            .add_event::<TerrainChanged>()
+            .add_event::<ApplyBuildingAt>()
+            .add_event::<BuildingChanged>()
+            .add_event::<ApplyFogOfWar>()
+            .add_event::<MapLoaded>()
+            .add_event::<MapUnloaded>()
+            .add_systems(
+                Update,
+                (
+                    handle_apply_terrain_at,
+                    handle_apply_building_at,
+                    (handle_apply_fog_of_war, hide_units_in_fog).chain(),
+                ),
+            );
+
+        // Without an asset server the map keeps the default terrain theme
+        if app.world().contains_resource::<AssetServer>() {
+            app.init_asset::<TerrainTheme>()
+                .init_asset_loader::<TerrainThemeLoader>()
+                .init_resource::<CurrentTerrainTheme>()
+                .add_systems(Update, apply_terrain_theme);
+        }
+        // and without rendering the water keeps its plain terrain material
+        if app.get_sub_app(RenderApp).is_some() {
+            app.add_plugins(MaterialPlugin::<WaterMaterial>::default())
+                .init_resource::<WaterMaterials>();
+        }
+    }
+}
+
+// Registers the asset type unless another plugin already did
+fn init_assets<A: Asset>(app: &mut App) {
+    if app.world().contains_resource::<Assets<A>>() {
+        return;
+    }
+    if app.world().contains_resource::<AssetServer>() {
+        app.init_asset::<A>();
+    } else {
+        app.init_resource::<Assets<A>>();
     }
 }
 
 impl MapModelPlugin {
+    // Replaces the current map model, if any, with one for `map`
     pub fn initialize_map_model(
         map: Map,
         commands: &mut Commands,
         meshes: &mut ResMut<Assets<Mesh>>,
         materials: &mut ResMut<Assets<StandardMaterial>>,
     ) -> Result<(), bool> {
+        Self::unload_map_model(commands);
         let map_model = MapModel::try_new(map, commands, meshes, materials).unwrap();
         commands.insert_resource(map_model);
+        commands.send_event(MapLoaded);
         Ok(())
     }
+
+    // Removes the map model and despawns everything it spawned. Does nothing without a map.
+    pub fn unload_map_model(commands: &mut Commands) {
+        commands.queue(|world: &mut World| {
+            let Some(map_model) = world.remove_resource::<MapModel>() else {
+                return;
+            };
+            for entity in map_model.owned_entities() {
+                if let Ok(entity) = world.get_entity_mut(entity) {
+                    entity.despawn();
+                }
+            }
+            world.send_event(MapUnloaded);
+        });
+    }
 }
 
+// Sent once a new map model has been inserted
+#[derive(Event, Clone, Copy, Debug)]
+pub struct MapLoaded;
+
+// Sent once the map model has been removed and its entities despawned
+#[derive(Event, Clone, Copy, Debug)]
+pub struct MapUnloaded;
+
 // Event sent by the editor when the user clicks in the viewport to paint a terrain
 #[derive(Event, Clone, Copy, Debug)]
 pub struct ApplyTerrainAt {
@@ -31,16 +106,383 @@ pub struct ApplyTerrainAt {
     pub terrain: battleisles_domain::map::Terrain,
 }
 
+// Event sent after an ApplyTerrainAt actually changed a tile, so edits can be recorded (e.g. for undo)
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TerrainChanged {
+    pub index: usize,
+    pub previous: battleisles_domain::map::Terrain,
+    pub terrain: battleisles_domain::map::Terrain,
+}
+
+// Applies every ApplyTerrainAt to the mirror image of the tile as well, e.g. so the editor
+// keeps multiplayer maps fair. None, the default, turns this off.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct TerrainSymmetry(pub Option<Symmetry>);
+
 fn handle_apply_terrain_at(
     mut ev: EventReader<ApplyTerrainAt>,
     map_model: Option<ResMut<MapModel>>, // may not exist until initialize_map_model runs
+    symmetry: Res<TerrainSymmetry>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut commands: Commands,
+    mut changed: EventWriter<TerrainChanged>,
 ) {
     let Some(mut map_model) = map_model else { return; };
     for ApplyTerrainAt { world_pos, terrain } in ev.read().copied() {
-        if let Some((idx, _entity)) = map_model.find_nearest_tile_entity(world_pos) {
-            map_model.set_tile_terrain(idx, terrain, &mut materials, &mut commands);
+        let Some((index, _entity)) = map_model.tile_entity_at(world_pos) else { continue; };
+        let map = map_model.map();
+        let mirrored = symmetry
+            .0
+            .and_then(|symmetry| map.mirror_hex(map.tiles()[index].position(), symmetry))
+            .and_then(|hex| map.tile_index(hex))
+            .filter(|mirrored| *mirrored != index);
+        for index in std::iter::once(index).chain(mirrored) {
+            let previous = map_model.set_tile_terrain(index, terrain, &mut materials, &mut commands);
+            if let Some(previous) = previous.filter(|previous| *previous != terrain) {
+                changed.write(TerrainChanged {
+                    index,
+                    previous,
+                    terrain,
+                });
+            }
+        }
+    }
+}
+
+// Event sent by the editor to place a building on (or, with `None`, remove it from) the clicked tile
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ApplyBuildingAt {
+    pub world_pos: Vec2,    // world coords in the main XY plane (already centered)
+    pub building: Option<Building>,
+}
+
+// Event sent after an ApplyBuildingAt actually changed a tile
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BuildingChanged {
+    pub index: usize,
+    pub previous: Option<Building>,
+    pub building: Option<Building>,
+}
+
+fn handle_apply_building_at(
+    mut ev: EventReader<ApplyBuildingAt>,
+    map_model: Option<ResMut<MapModel>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+    mut changed: EventWriter<BuildingChanged>,
+) {
+    let Some(mut map_model) = map_model else { return; };
+    for ApplyBuildingAt { world_pos, building } in ev.read().copied() {
+        let Some((index, _entity)) = map_model.tile_entity_at(world_pos) else { continue; };
+        if map_model.map().tiles()[index].building == building {
+            continue;
+        }
+        if let Some(previous) = map_model.set_tile_building(index, building, &mut materials, &mut commands) {
+            changed.write(BuildingChanged {
+                index,
+                previous,
+                building,
+            });
+        }
+    }
+}
+
+// Event sent by the game to show the map as a player knows it (see MapModel::set_fog_of_war)
+#[derive(Event, Clone, Debug)]
+pub struct ApplyFogOfWar {
+    pub viewer: Option<PlayerId>,
+    pub tiles: Vec<TileVisibility>,
+}
+
+// Marks an entity that stands on a map tile, such as a unit, so the fog of war can hide it
+#[derive(Component, Clone, Copy, Debug)]
+pub struct MapUnit {
+    pub position: Hex,
+    pub owner: PlayerId,
+}
+
+fn handle_apply_fog_of_war(
+    mut ev: EventReader<ApplyFogOfWar>,
+    map_model: Option<ResMut<MapModel>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    let Some(mut map_model) = map_model else { return; };
+    // Only the latest view matters
+    if let Some(ApplyFogOfWar { viewer, tiles }) = ev.read().last() {
+        map_model.set_fog_of_war(*viewer, tiles, &mut materials, &mut commands);
+    }
+}
+
+// The viewer's own units are always shown, other units only on tiles the viewer currently sees
+fn hide_units_in_fog(
+    map_model: Option<Res<MapModel>>,
+    mut units: Query<(&MapUnit, &mut Visibility)>,
+) {
+    let Some(map_model) = map_model else { return; };
+    for (unit, mut visibility) in units.iter_mut() {
+        let shown = match map_model.fog_viewer() {
+            None => true,
+            Some(viewer) => {
+                unit.owner == viewer
+                    || map_model.tile_visibility(unit.position) == TileVisibility::Visible
+            }
+        };
+        visibility.set_if_neq(if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+// Restyles the terrain when the theme file has been (re)loaded and whenever a new map model
+// is created, since that starts out with the default theme
+#[allow(clippy::too_many_arguments)]
+fn apply_terrain_theme(
+    mut theme_events: EventReader<AssetEvent<TerrainTheme>>,
+    themes: Res<Assets<TerrainTheme>>,
+    mut current: ResMut<CurrentTerrainTheme>,
+    asset_server: Res<AssetServer>,
+    map_model: Option<ResMut<MapModel>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    // Only there when rendering
+    water: Option<Res<WaterMaterials>>,
+    water_materials: Option<ResMut<Assets<WaterMaterial>>>,
+) {
+    let id = current.id();
+    let reloaded = theme_events
+        .read()
+        .filter(|event| event.is_loaded_with_dependencies(id) || event.is_modified(id))
+        .count()
+        > 0;
+    if let Some(theme) = themes.get(id).filter(|_| reloaded) {
+        current.set(theme.clone(), &asset_server);
+        if let (Some(water), Some(mut water_materials)) = (water, water_materials) {
+            water.set_theme(current.theme(), &asset_server, &mut water_materials);
+        }
+    }
+    let Some(mut map_model) = map_model else {
+        return;
+    };
+    if reloaded || map_model.is_added() {
+        map_model.set_terrain_theme(current.theme(), &asset_server, &mut materials);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_model::TileIndex;
+    use battleisles_domain::map::{HexOrientation, MapShape, Terrain};
+    use bevy::ecs::event::Events;
+    use rstest::rstest;
+
+    // Headless, without a window, an asset server or a GPU
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, MapModelPlugin));
+        app
+    }
+
+    fn app_with_map(map: Map) -> App {
+        let mut app = app();
+        app.world_mut().run_system_cached_with(load, map).unwrap();
+        app.update();
+        app
+    }
+
+    fn load(
+        In(map): In<Map>,
+        mut commands: Commands,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+    ) {
+        MapModelPlugin::initialize_map_model(map, &mut commands, &mut meshes, &mut materials)
+            .unwrap();
+    }
+
+    fn unload(mut commands: Commands) {
+        MapModelPlugin::unload_map_model(&mut commands);
+    }
+
+    fn entity_count(app: &App) -> u32 {
+        app.world().entities().len()
+    }
+
+    fn event_count<E: Event>(app: &App) -> usize {
+        app.world().resource::<Events<E>>().len()
+    }
+
+    fn paint(app: &mut App, hex: Hex, terrain: Terrain) {
+        let world_pos = app.world().resource::<MapModel>().tile_world_pos(hex).unwrap();
+        app.world_mut().send_event(ApplyTerrainAt { world_pos, terrain });
+        app.update();
+    }
+
+    fn tile_entity(app: &mut App, index: usize) -> Entity {
+        let mut tiles = app.world_mut().query::<(Entity, &TileIndex)>();
+        let mut entities = tiles
+            .iter(app.world())
+            .filter(|(_, TileIndex(i))| *i == index)
+            .map(|(entity, _)| entity);
+        let entity = entities.next().unwrap();
+        assert_eq!(entities.next(), None);
+        entity
+    }
+
+    fn tile_color(app: &mut App, index: usize) -> Color {
+        let entity = tile_entity(app, index);
+        let material = app.world().get::<MeshMaterial3d<StandardMaterial>>(entity).unwrap();
+        let materials = app.world().resource::<Assets<StandardMaterial>>();
+        materials.get(&material.0).unwrap().base_color
+    }
+
+    #[test]
+    fn test_regenerating_a_map_does_not_leak_entities() {
+        let mut app = app();
+        // Registered systems are entities too
+        app.world_mut().register_system_cached(load);
+        app.world_mut().register_system_cached(unload);
+        let empty = entity_count(&app);
+
+        app.world_mut().run_system_cached_with(load, Map::new(6, 4)).unwrap();
+        let loaded = entity_count(&app);
+        assert!(loaded > empty);
+        for _ in 0..3 {
+            app.world_mut().run_system_cached_with(load, Map::new(6, 4)).unwrap();
+            assert_eq!(entity_count(&app), loaded);
+        }
+        assert_eq!(event_count::<MapLoaded>(&app), 4);
+        assert_eq!(event_count::<MapUnloaded>(&app), 3);
+
+        app.world_mut().run_system_cached(unload).unwrap();
+        assert_eq!(entity_count(&app), empty);
+        assert!(app.world().get_resource::<MapModel>().is_none());
+        assert_eq!(event_count::<MapUnloaded>(&app), 4);
+    }
+
+    #[rstest]
+    #[case(Map::new(5, 3))]
+    #[case(Map::with_shape(MapShape::Hexagon { radius: 2 }, HexOrientation::Flat))]
+    #[case(Map::with_shape(MapShape::Parallelogram { width: 4, height: 3 }, HexOrientation::Pointy))]
+    fn test_every_tile_gets_an_entity(#[case] map: Map) {
+        let count = map.tiles().len();
+        let mut app = app_with_map(map);
+        let mut tiles = app.world_mut().query::<&TileIndex>();
+        let mut indices = tiles.iter(app.world()).map(|TileIndex(i)| *i).collect::<Vec<_>>();
+        indices.sort();
+        assert_eq!(indices, (0..count).collect::<Vec<_>>());
+        // And is picked where it is spawned
+        let map_model = app.world().resource::<MapModel>();
+        for tile in map_model.map().tiles() {
+            let world_pos = map_model.tile_world_pos(tile.position()).unwrap();
+            assert_eq!(map_model.hex_at(world_pos), Some(tile.position()));
+        }
+    }
+
+    #[rstest]
+    #[case(HexOrientation::Pointy, Vec2::Y)]
+    #[case(HexOrientation::Flat, Vec2::X)]
+    fn test_tile_meshes_match_the_orientation(
+        #[case] orientation: HexOrientation,
+        #[case] corner: Vec2,
+    ) {
+        let map = Map::with_shape(MapShape::Rectangle { width: 2, height: 2 }, orientation);
+        let hex_size = map.hex_size();
+        let mut app = app_with_map(map);
+        let entity = tile_entity(&mut app, 0);
+        let mesh = app.world().get::<Mesh3d>(entity).unwrap();
+        let meshes = app.world().resource::<Assets<Mesh>>();
+        let positions = meshes.get(&mesh.0).unwrap().attribute(Mesh::ATTRIBUTE_POSITION);
+        let positions = positions.unwrap().as_float3().unwrap();
+        let has_corner = |corner: Vec2| {
+            positions
+                .iter()
+                .any(|p| Vec2::new(p[0], p[1]).distance(corner * hex_size) < 1e-4)
+        };
+        assert!(has_corner(corner));
+        // The other orientation has its corners where this one has edges
+        assert!(!has_corner(corner.perp()));
+    }
+
+    #[rstest]
+    #[case(Terrain::Mountains)]
+    #[case(Terrain::Hills)]
+    // Stays a standard material, the animated water needs rendering
+    #[case(Terrain::ShallowWater)]
+    fn test_apply_terrain_at_changes_the_tile(#[case] terrain: Terrain) {
+        let mut app = app_with_map(Map::new(4, 4));
+        let index = 6;
+        let tile = app.world().resource::<MapModel>().map().tiles()[index].clone();
+        assert_ne!(tile.terrain, terrain);
+
+        paint(&mut app, tile.position(), terrain);
+
+        let map = app.world().resource::<MapModel>().map();
+        assert_eq!(map.tiles()[index].terrain, terrain);
+        assert_eq!(
+            tile_color(&mut app, index),
+            TerrainTheme::default().style(terrain).base_color.into()
+        );
+        let events = app.world().resource::<Events<TerrainChanged>>();
+        let changes = events.iter_current_update_events().collect::<Vec<_>>();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].index, index);
+        assert_eq!(changes[0].previous, tile.terrain);
+        assert_eq!(changes[0].terrain, terrain);
+    }
+
+    #[rstest]
+    #[case(Symmetry::Horizontal, (0, 0), (4, 0))]
+    #[case(Symmetry::Vertical, (1, 0), (1, 4))]
+    #[case(Symmetry::Point, (0, 1), (3, 3))]
+    fn test_apply_terrain_at_mirrors_with_symmetry(
+        #[case] symmetry: Symmetry,
+        #[case] painted: (i32, i32),
+        #[case] mirrored: (i32, i32),
+    ) {
+        let mut app = app_with_map(Map::new(5, 5));
+        app.insert_resource(TerrainSymmetry(Some(symmetry)));
+        let map = app.world().resource::<MapModel>().map();
+        let painted = map.tile_at_offset(painted.0, painted.1).unwrap().position();
+        let mirrored = map.tile_at_offset(mirrored.0, mirrored.1).unwrap().position();
+        let mirrored_index = map.tile_index(mirrored).unwrap();
+
+        paint(&mut app, painted, Terrain::Hills);
+
+        let map = app.world().resource::<MapModel>().map();
+        assert_eq!(map.tile_at(painted).unwrap().terrain, Terrain::Hills);
+        assert_eq!(map.tile_at(mirrored).unwrap().terrain, Terrain::Hills);
+        let changed = map.tiles().iter().filter(|tile| tile.terrain == Terrain::Hills);
+        assert_eq!(changed.count(), 2);
+        assert_eq!(
+            tile_color(&mut app, mirrored_index),
+            TerrainTheme::default().style(Terrain::Hills).base_color.into()
+        );
+        assert_eq!(event_count::<TerrainChanged>(&app), 2);
+    }
+
+    #[test]
+    fn test_apply_terrain_at_leaves_other_tiles_alone() {
+        let mut app = app_with_map(Map::new(4, 4));
+        let before = app.world().resource::<MapModel>().map().tiles().to_vec();
+
+        paint(&mut app, before[6].position(), Terrain::Mountains);
+        // Painting the same terrain again is not a change
+        paint(&mut app, before[6].position(), Terrain::Mountains);
+        app.world_mut().send_event(ApplyTerrainAt {
+            world_pos: Vec2::splat(1000.0),
+            terrain: Terrain::Hills,
+        });
+        app.update();
+
+        let after = app.world().resource::<MapModel>().map().tiles();
+        for (i, (before, after)) in before.iter().zip(after).enumerate() {
+            if i != 6 {
+                assert_eq!(before.terrain, after.terrain);
+            }
         }
+        assert_eq!(event_count::<TerrainChanged>(&app), 1);
     }
 }
@@ -1,31 +1,76 @@
+use crate::terrain_theme::TerrainTheme;
 use battleisles_domain::map::Terrain;
+use battleisles_domain::visibility::TileVisibility;
 use bevy::prelude::*;
-use bevy_color::palettes::basic::*;
 use std::collections::HashMap;
 
+// Unexplored tiles all look the same so they give nothing away
+const UNEXPLORED: Srgba = Srgba::rgb(0.08, 0.08, 0.1);
+
 #[derive(Resource, Default)]
 pub struct TerrainMaterials {
-    cache: HashMap<Terrain, Handle<StandardMaterial>>,
+    theme: TerrainTheme,
+    textures: HashMap<Terrain, Handle<Image>>,
+    cache: HashMap<(Terrain, TileVisibility), Handle<StandardMaterial>>,
 }
 
 impl TerrainMaterials {
     pub fn get_or_create(
         &mut self,
         terrain: Terrain,
+        visibility: TileVisibility,
         materials: &mut Assets<StandardMaterial>,
     ) -> Handle<StandardMaterial> {
-        self.cache
-            .entry(terrain)
-            .or_insert_with(|| {
-                let color = match terrain {
-                    Terrain::Plains => GREEN,
-                    Terrain::Hills => OLIVE,
-                    Terrain::Mountains => GRAY,
-                    Terrain::DeepWater => BLUE,
-                    Terrain::ShallowWater => AQUA,
-                };
-                materials.add(StandardMaterial::from_color(color))
+        if let Some(handle) = self.cache.get(&(terrain, visibility)) {
+            return handle.clone();
+        }
+        let handle = materials.add(self.material(terrain, visibility));
+        self.cache.insert((terrain, visibility), handle.clone());
+        handle
+    }
+
+    // Restyles the materials handed out so far in place, so tiles pick up the new theme
+    // without being touched
+    pub fn set_theme(
+        &mut self,
+        theme: &TerrainTheme,
+        asset_server: &AssetServer,
+        materials: &mut Assets<StandardMaterial>,
+    ) {
+        self.theme = theme.clone();
+        self.textures = Terrain::ALL
+            .into_iter()
+            .filter_map(|terrain| {
+                let path = self.theme.style(terrain).texture.clone()?;
+                Some((terrain, asset_server.load(path)))
             })
-            .clone()
+            .collect();
+        for (&(terrain, visibility), handle) in &self.cache {
+            if let Some(material) = materials.get_mut(handle) {
+                *material = self.material(terrain, visibility);
+            }
+        }
     }
+
+    fn material(&self, terrain: Terrain, visibility: TileVisibility) -> StandardMaterial {
+        let style = self.theme.style(terrain);
+        let color = match visibility {
+            TileVisibility::Visible => style.base_color,
+            TileVisibility::Remembered => remembered(style.base_color),
+            TileVisibility::Unexplored => return StandardMaterial::from_color(UNEXPLORED),
+        };
+        StandardMaterial {
+            base_color: color.into(),
+            base_color_texture: self.textures.get(&terrain).cloned(),
+            perceptual_roughness: style.roughness,
+            ..default()
+        }
+    }
+}
+
+// Mostly desaturated and somewhat darker, so remembered tiles stay recognisable
+fn remembered(color: Srgba) -> Srgba {
+    let luminance = 0.3 * color.red + 0.59 * color.green + 0.11 * color.blue;
+    let mix = |channel: f32| (channel + (luminance - channel) * 0.7) * 0.7;
+    Srgba::rgb(mix(color.red), mix(color.green), mix(color.blue))
 }
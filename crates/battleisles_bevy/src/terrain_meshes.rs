@@ -0,0 +1,96 @@
+use battleisles_domain::map::{Elevation, HexOrientation, Terrain};
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use std::collections::HashMap;
+
+// Height of each terrain's top face above the map plane, relative to a hex size of 1
+pub fn terrain_height(terrain: Terrain) -> f32 {
+    match terrain {
+        Terrain::DeepWater => 0.05,
+        Terrain::ShallowWater => 0.1,
+        Terrain::Plains => 0.2,
+        Terrain::Hills => 0.4,
+        Terrain::Mountains => 0.8,
+    }
+}
+
+// One bevelled hex prism per terrain, all tiles of a terrain share it
+pub struct TerrainMeshes {
+    meshes: HashMap<Terrain, Handle<Mesh>>,
+}
+
+impl TerrainMeshes {
+    pub fn new(hex_size: f32, orientation: HexOrientation, meshes: &mut Assets<Mesh>) -> Self {
+        let meshes = Terrain::ALL
+            .into_iter()
+            .map(|terrain| {
+                let height = terrain_height(terrain) * hex_size;
+                // Water gets a barely visible edge, land a clear bevel
+                let bevel = if terrain.elevation() == Elevation::Water {
+                    0.02
+                } else {
+                    0.08
+                } * hex_size;
+                (
+                    terrain,
+                    meshes.add(bevelled_hex_prism(hex_size, height, bevel, orientation)),
+                )
+            })
+            .collect();
+        TerrainMeshes { meshes }
+    }
+
+    pub fn get(&self, terrain: Terrain) -> Handle<Mesh> {
+        self.meshes[&terrain].clone()
+    }
+}
+
+// Hexagonal prism standing on z = 0 with its top edges bevelled. It has no bottom face
+// since the map is only ever seen from above.
+fn bevelled_hex_prism(radius: f32, height: f32, bevel: f32, orientation: HexOrientation) -> Mesh {
+    // Flat-top hexes have a corner on the X axis, pointy-top ones are turned by 30°
+    let first_corner = match orientation {
+        HexOrientation::Pointy => std::f32::consts::FRAC_PI_6,
+        HexOrientation::Flat => 0.0,
+    };
+    let corner = |i: usize, r: f32, z: f32| {
+        let angle = first_corner + i as f32 * std::f32::consts::FRAC_PI_3;
+        Vec3::new(angle.cos() * r, angle.sin() * r, z)
+    };
+    let inner = radius - bevel;
+    let shoulder = height - bevel;
+
+    let mut positions = Vec::with_capacity(6 * 3 * 5);
+    for i in 0..6 {
+        let j = (i + 1) % 6;
+        // Top face
+        positions.extend([
+            Vec3::Z * height,
+            corner(i, inner, height),
+            corner(j, inner, height),
+        ]);
+        // Bevel and side wall, both counter-clockwise seen from outside
+        for (lo_r, lo_z, hi_r, hi_z) in [
+            (radius, shoulder, inner, height),
+            (radius, 0.0, radius, shoulder),
+        ] {
+            let (a_lo, b_lo) = (corner(i, lo_r, lo_z), corner(j, lo_r, lo_z));
+            let (a_hi, b_hi) = (corner(i, hi_r, hi_z), corner(j, hi_r, hi_z));
+            positions.extend([a_lo, b_lo, b_hi, a_lo, b_hi, a_hi]);
+        }
+    }
+    // Planar projection from above onto the hex's bounding square
+    let uvs = positions
+        .iter()
+        .map(|p| [p.x / (2.0 * radius) + 0.5, 0.5 - p.y / (2.0 * radius)])
+        .collect::<Vec<_>>();
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_computed_flat_normals()
+}
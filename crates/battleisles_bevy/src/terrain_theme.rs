@@ -0,0 +1,246 @@
+use battleisles_domain::map::Terrain;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::fmt;
+
+pub const TERRAIN_THEME_PATH: &str = "themes/terrain.theme.ron";
+
+// How a terrain is drawn on the map and shown in the editor palette
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct TerrainStyle {
+    // Written as a hex string such as "#808000"
+    #[serde(deserialize_with = "hex_color")]
+    pub base_color: Srgba,
+    // Asset paths; the texture is tinted with the base colour
+    #[serde(default)]
+    pub texture: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default = "default_roughness")]
+    pub roughness: f32,
+}
+
+impl TerrainStyle {
+    fn new(base_color: Srgba, roughness: f32) -> Self {
+        TerrainStyle {
+            base_color,
+            texture: None,
+            icon: None,
+            roughness,
+        }
+    }
+}
+
+fn default_roughness() -> f32 {
+    0.8
+}
+
+fn hex_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Srgba, D::Error> {
+    let hex = String::deserialize(deserializer)?;
+    Srgba::hex(&hex).map_err(|e| serde::de::Error::custom(format!("{hex}: {e}")))
+}
+
+// Look of every terrain, loaded from a RON file so it can be changed without touching the
+// code. Terrains the file leaves out keep their default style.
+#[derive(Asset, TypePath, Deserialize, Clone, Debug)]
+pub struct TerrainTheme {
+    terrains: HashMap<Terrain, TerrainStyle>,
+}
+
+impl TerrainTheme {
+    // Every terrain has a style, the file's or the default one
+    pub fn style(&self, terrain: Terrain) -> &TerrainStyle {
+        &self.terrains[&terrain]
+    }
+
+    // Parses a theme file, filling in the terrains it leaves out
+    pub fn from_ron_bytes(bytes: &[u8]) -> Result<Self, TerrainThemeError> {
+        let mut theme: TerrainTheme = ron::de::from_bytes(bytes)?;
+        for (terrain, style) in TerrainTheme::default().terrains {
+            theme.terrains.entry(terrain).or_insert(style);
+        }
+        Ok(theme)
+    }
+}
+
+// Used until the theme file is loaded, or if it cannot be
+impl Default for TerrainTheme {
+    fn default() -> Self {
+        use bevy_color::palettes::basic::*;
+        let terrains = [
+            (Terrain::Plains, TerrainStyle::new(GREEN, 0.8)),
+            (Terrain::Hills, TerrainStyle::new(OLIVE, 0.8)),
+            (Terrain::Mountains, TerrainStyle::new(GRAY, 0.9)),
+            (
+                Terrain::DeepWater,
+                TerrainStyle::new(Srgba::rgb_u8(0x05, 0x1F, 0x59), 0.1),
+            ),
+            (
+                Terrain::ShallowWater,
+                TerrainStyle::new(Srgba::rgb_u8(0x1A, 0x73, 0x8C), 0.1),
+            ),
+        ];
+        TerrainTheme {
+            terrains: terrains.into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TerrainThemeError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+}
+
+impl fmt::Display for TerrainThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TerrainThemeError::Io(e) => write!(f, "I/O error: {e}"),
+            TerrainThemeError::Parse(e) => write!(f, "malformed terrain theme: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for TerrainThemeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TerrainThemeError::Io(e) => Some(e),
+            TerrainThemeError::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for TerrainThemeError {
+    fn from(e: std::io::Error) -> Self {
+        TerrainThemeError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for TerrainThemeError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        TerrainThemeError::Parse(e)
+    }
+}
+
+#[derive(Default)]
+pub struct TerrainThemeLoader;
+
+impl AssetLoader for TerrainThemeLoader {
+    type Asset = TerrainTheme;
+    type Settings = ();
+    type Error = TerrainThemeError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<TerrainTheme, TerrainThemeError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        TerrainTheme::from_ron_bytes(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["theme.ron"]
+    }
+}
+
+// The theme in use, a copy of the loaded asset that is refreshed whenever the file changes
+// (with the `hot_reload` feature). Icons are loaded here so the editor palette can show them.
+#[derive(Resource)]
+pub struct CurrentTerrainTheme {
+    handle: Handle<TerrainTheme>,
+    theme: TerrainTheme,
+    icons: HashMap<Terrain, Handle<Image>>,
+}
+
+impl CurrentTerrainTheme {
+    pub fn theme(&self) -> &TerrainTheme {
+        &self.theme
+    }
+
+    pub fn icon(&self, terrain: Terrain) -> Option<Handle<Image>> {
+        self.icons.get(&terrain).cloned()
+    }
+
+    pub(crate) fn id(&self) -> AssetId<TerrainTheme> {
+        self.handle.id()
+    }
+
+    pub(crate) fn set(&mut self, theme: TerrainTheme, asset_server: &AssetServer) {
+        self.icons = Terrain::ALL
+            .into_iter()
+            .filter_map(|terrain| {
+                let path = theme.style(terrain).icon.clone()?;
+                Some((terrain, asset_server.load(path)))
+            })
+            .collect();
+        self.theme = theme;
+    }
+}
+
+impl FromWorld for CurrentTerrainTheme {
+    fn from_world(world: &mut World) -> Self {
+        CurrentTerrainTheme {
+            handle: world.resource::<AssetServer>().load(TERRAIN_THEME_PATH),
+            theme: TerrainTheme::default(),
+            icons: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn shipped_theme() -> Vec<u8> {
+        let assets = concat!(env!("CARGO_MANIFEST_DIR"), "/../../assets");
+        std::fs::read(format!("{assets}/{TERRAIN_THEME_PATH}")).unwrap()
+    }
+
+    #[rstest]
+    #[case(Terrain::Plains, "#008000", 0.8)]
+    #[case(Terrain::Hills, "#808000", 0.8)]
+    #[case(Terrain::Mountains, "#808080", 0.9)]
+    #[case(Terrain::DeepWater, "#051F59", 0.1)]
+    #[case(Terrain::ShallowWater, "#1A738C", 0.1)]
+    fn test_shipped_theme(#[case] terrain: Terrain, #[case] color: &str, #[case] roughness: f32) {
+        let sut = TerrainTheme::from_ron_bytes(&shipped_theme()).unwrap();
+        let style = sut.style(terrain);
+        assert_eq!(style.base_color, Srgba::hex(color).unwrap());
+        assert_eq!(style.roughness, roughness);
+    }
+
+    #[test]
+    fn test_left_out_terrains_keep_their_default_style() {
+        let text = "(terrains: { Hills: (base_color: \"#A0A000\", icon: Some(\"hills.png\")) })";
+
+        let sut = TerrainTheme::from_ron_bytes(text.as_bytes()).unwrap();
+
+        let hills = sut.style(Terrain::Hills);
+        assert_eq!(hills.base_color, Srgba::hex("#A0A000").unwrap());
+        assert_eq!(hills.icon.as_deref(), Some("hills.png"));
+        assert_eq!(hills.texture, None);
+        assert_eq!(hills.roughness, default_roughness());
+        let defaults = TerrainTheme::default();
+        for terrain in Terrain::ALL.into_iter().filter(|t| *t != Terrain::Hills) {
+            assert_eq!(sut.style(terrain), defaults.style(terrain));
+        }
+    }
+
+    #[rstest]
+    #[case("(terrains: { Hills: (base_color: \"olive\") })")]
+    #[case("(terrains: { Swamp: (base_color: \"#808000\") })")]
+    #[case("(terrains: { Hills: (roughness: 0.5) })")]
+    fn test_malformed_theme_is_rejected(#[case] text: &str) {
+        assert!(matches!(
+            TerrainTheme::from_ron_bytes(text.as_bytes()),
+            Err(TerrainThemeError::Parse(_))
+        ));
+    }
+}
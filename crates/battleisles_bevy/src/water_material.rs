@@ -0,0 +1,145 @@
+use crate::terrain_theme::TerrainTheme;
+use battleisles_domain::map::Terrain;
+use bevy::image::{
+    ImageAddressMode, ImageFilterMode, ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor,
+};
+use bevy::pbr::{ExtendedMaterial, MaterialExtension};
+use bevy::prelude::*;
+use bevy::render::render_resource::{AsBindGroup, ShaderRef};
+
+pub use settings::WaterSettings;
+
+const WATER_SHADER_PATH: &str = "shaders/water_material.wgsl";
+const WATER_NORMALS_PATH: &str = "textures/water_normals.png";
+
+pub type WaterMaterial = ExtendedMaterial<StandardMaterial, Water>;
+
+// Animated ripples on top of the standard material, see the water shader
+#[derive(Asset, AsBindGroup, TypePath, Debug, Clone)]
+pub struct Water {
+    #[texture(100)]
+    #[sampler(101)]
+    pub normals: Handle<Image>,
+    #[uniform(102)]
+    pub settings: WaterSettings,
+}
+
+// The `ShaderType` derive emits layout checks that rustc reports as never used
+#[allow(dead_code)]
+mod settings {
+    use bevy::prelude::*;
+    use bevy::render::render_resource::ShaderType;
+
+    #[derive(ShaderType, Debug, Clone)]
+    pub struct WaterSettings {
+        // Drift per second of the four octaves, two packed into each vector
+        pub octave_vectors: [Vec4; 2],
+        pub octave_scales: Vec4,
+        pub octave_strengths: Vec4,
+    }
+
+    impl Default for WaterSettings {
+        fn default() -> Self {
+            WaterSettings {
+                octave_vectors: [
+                    Vec4::new(0.080, 0.059, 0.073, -0.062),
+                    Vec4::new(0.153, 0.138, -0.149, -0.195),
+                ],
+                octave_scales: Vec4::new(1.0, 2.1, 7.9, 14.9) * 0.25,
+                octave_strengths: Vec4::new(0.16, 0.18, 0.093, 0.044),
+            }
+        }
+    }
+}
+
+impl MaterialExtension for Water {
+    // The shader covers the forward pass as well, so the water also works without a
+    // deferred prepass on the camera
+    fn fragment_shader() -> ShaderRef {
+        WATER_SHADER_PATH.into()
+    }
+
+    fn deferred_fragment_shader() -> ShaderRef {
+        WATER_SHADER_PATH.into()
+    }
+}
+
+// One material per water terrain, shared by all visible tiles of that terrain. Tiles only
+// use it while this resource exists, otherwise they keep their plain terrain material.
+#[derive(Resource)]
+pub struct WaterMaterials {
+    deep: Handle<WaterMaterial>,
+    shallow: Handle<WaterMaterial>,
+}
+
+impl WaterMaterials {
+    pub fn get(&self, terrain: Terrain) -> Option<Handle<WaterMaterial>> {
+        match terrain {
+            Terrain::DeepWater => Some(self.deep.clone()),
+            Terrain::ShallowWater => Some(self.shallow.clone()),
+            _ => None,
+        }
+    }
+
+    // Takes the tint, texture and roughness of the water terrains from the theme
+    pub fn set_theme(
+        &self,
+        theme: &TerrainTheme,
+        asset_server: &AssetServer,
+        materials: &mut Assets<WaterMaterial>,
+    ) {
+        for (terrain, handle) in [
+            (Terrain::DeepWater, &self.deep),
+            (Terrain::ShallowWater, &self.shallow),
+        ] {
+            let Some(material) = materials.get_mut(handle) else {
+                continue;
+            };
+            let style = theme.style(terrain);
+            material.base.base_color = style.base_color.into();
+            material.base.base_color_texture =
+                style.texture.clone().map(|path| asset_server.load(path));
+            material.base.perceptual_roughness = style.roughness;
+        }
+    }
+}
+
+impl FromWorld for WaterMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let normals = world.resource::<AssetServer>().load_with_settings(
+            WATER_NORMALS_PATH,
+            |settings: &mut ImageLoaderSettings| {
+                settings.is_srgb = false;
+                settings.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+                    address_mode_u: ImageAddressMode::Repeat,
+                    address_mode_v: ImageAddressMode::Repeat,
+                    mag_filter: ImageFilterMode::Linear,
+                    min_filter: ImageFilterMode::Linear,
+                    ..default()
+                });
+            },
+        );
+        let mut materials = world.resource_mut::<Assets<WaterMaterial>>();
+        // Tinted with the default theme until the terrain theme is applied
+        let theme = TerrainTheme::default();
+        let mut water = |terrain: Terrain| {
+            let style = theme.style(terrain);
+            materials.add(WaterMaterial {
+                base: StandardMaterial {
+                    base_color: style.base_color.into(),
+                    perceptual_roughness: style.roughness,
+                    reflectance: 0.6,
+                    ..default()
+                },
+                extension: Water {
+                    normals: normals.clone(),
+                    settings: WaterSettings::default(),
+                },
+            })
+        };
+        WaterMaterials {
+            deep: water(Terrain::DeepWater),
+            shallow: water(Terrain::ShallowWater),
+        }
+    }
+}
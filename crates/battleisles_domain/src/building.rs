@@ -0,0 +1,133 @@
+use crate::pathfinding::MovementClass;
+use crate::player::PlayerId;
+use serde::{Deserialize, Serialize};
+
+#[derive(PartialEq, Clone, Debug, Copy, Eq, Hash, Serialize, Deserialize)]
+pub enum BuildingKind {
+    Headquarters,
+    Factory,
+    Depot,
+    Airfield,
+    Harbour,
+}
+
+impl BuildingKind {
+    pub const ALL: [BuildingKind; 5] = [
+        BuildingKind::Headquarters,
+        BuildingKind::Factory,
+        BuildingKind::Depot,
+        BuildingKind::Airfield,
+        BuildingKind::Harbour,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BuildingKind::Headquarters => "Headquarters",
+            BuildingKind::Factory => "Factory",
+            BuildingKind::Depot => "Depot",
+            BuildingKind::Airfield => "Airfield",
+            BuildingKind::Harbour => "Harbour",
+        }
+    }
+
+    // Energy the owner collects from the building at the start of each of their turns
+    pub fn income(self) -> u32 {
+        match self {
+            BuildingKind::Headquarters => 200,
+            BuildingKind::Factory | BuildingKind::Airfield | BuildingKind::Harbour => 100,
+            BuildingKind::Depot => 50,
+        }
+    }
+
+    // Radius in hexes the owner sees around the building
+    pub fn vision(self) -> u32 {
+        match self {
+            BuildingKind::Headquarters => 3,
+            _ => 2,
+        }
+    }
+
+    // Number of units that fit inside, which limits production
+    pub fn capacity(self) -> usize {
+        match self {
+            BuildingKind::Headquarters | BuildingKind::Depot => 1,
+            BuildingKind::Factory => 6,
+            BuildingKind::Airfield | BuildingKind::Harbour => 4,
+        }
+    }
+
+    // Whether units of this movement class can be built here
+    pub fn produces(self, class: MovementClass) -> bool {
+        use MovementClass::*;
+        matches!(
+            (self, class),
+            (BuildingKind::Factory, Land | Tracked)
+                | (BuildingKind::Airfield, Air)
+                | (BuildingKind::Harbour, Naval)
+        )
+    }
+
+    // Whether units of this movement class standing here regain health at the start of their turn
+    pub fn repairs(self, class: MovementClass) -> bool {
+        self.produces(class) || (self == BuildingKind::Headquarters && self.resupplies(class))
+    }
+
+    // Whether units of this movement class standing here get their ammo and fuel refilled
+    pub fn resupplies(self, class: MovementClass) -> bool {
+        use MovementClass::*;
+        match self {
+            BuildingKind::Headquarters => matches!(class, Land | Tracked),
+            BuildingKind::Depot => matches!(class, Land | Tracked | Air),
+            _ => self.produces(class),
+        }
+    }
+
+    // Whether units of this movement class can enter the tile regardless of its terrain,
+    // e.g. ships docking in a harbour
+    pub fn admits(self, class: MovementClass) -> bool {
+        self.produces(class) || self.resupplies(class)
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Copy, Eq, Hash, Serialize, Deserialize)]
+pub struct Building {
+    pub kind: BuildingKind,
+    // `None` while the building is neutral
+    pub owner: Option<PlayerId>,
+}
+
+impl Building {
+    // Health a unit regains per turn on a building that repairs it
+    pub const REPAIR_PER_TURN: u32 = 2;
+
+    pub fn new(kind: BuildingKind, owner: Option<PlayerId>) -> Self {
+        Building { kind, owner }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(BuildingKind::Factory, MovementClass::Tracked, true, true, true)]
+    #[case(BuildingKind::Factory, MovementClass::Air, false, false, false)]
+    #[case(BuildingKind::Airfield, MovementClass::Air, true, true, true)]
+    #[case(BuildingKind::Harbour, MovementClass::Naval, true, true, true)]
+    #[case(BuildingKind::Headquarters, MovementClass::Land, false, true, true)]
+    #[case(BuildingKind::Depot, MovementClass::Air, false, false, true)]
+    #[case(BuildingKind::Depot, MovementClass::Naval, false, false, false)]
+    fn test_building_services(
+        #[case] kind: BuildingKind,
+        #[case] class: MovementClass,
+        #[case] produces: bool,
+        #[case] repairs: bool,
+        #[case] resupplies: bool,
+    ) {
+        assert_eq!(kind.produces(class), produces);
+        assert_eq!(kind.repairs(class), repairs);
+        assert_eq!(kind.resupplies(class), resupplies);
+        assert_eq!(kind.admits(class), produces || resupplies);
+    }
+}
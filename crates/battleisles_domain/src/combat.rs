@@ -0,0 +1,500 @@
+use crate::map::Terrain;
+use crate::rng::SeededRng;
+use crate::unit::{TargetClass, Unit, UnitType};
+use std::fmt;
+
+// Strength bonus per experience level
+const EXPERIENCE_BONUS: f64 = 0.1;
+
+// Defence bonus a ground unit gets from the terrain it stands on
+pub fn terrain_defence_bonus(terrain: Terrain) -> f64 {
+    match terrain {
+        Terrain::Plains => 0.0,
+        Terrain::Hills => 0.25,
+        Terrain::Mountains => 0.5,
+        Terrain::ShallowWater | Terrain::DeepWater => 0.0,
+    }
+}
+
+// One side of a fight: the unit, its type and the terrain it stands on
+#[derive(Clone, Copy, Debug)]
+pub struct Combatant<'a> {
+    pub unit: &'a Unit,
+    pub unit_type: &'a UnitType,
+    pub terrain: Terrain,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum CombatError {
+    SameOwner,
+    OutOfRange { distance: u32 },
+    CannotTarget,
+    NoAmmo,
+    Destroyed,
+    AlreadyAttacked,
+}
+
+impl fmt::Display for CombatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CombatError::SameOwner => write!(f, "units of the same player cannot fight"),
+            CombatError::OutOfRange { distance } => {
+                write!(f, "target at distance {distance} is out of range")
+            }
+            CombatError::CannotTarget => write!(f, "unit cannot attack this kind of target"),
+            CombatError::NoAmmo => write!(f, "unit is out of ammo"),
+            CombatError::Destroyed => write!(f, "destroyed units cannot fight"),
+            CombatError::AlreadyAttacked => write!(f, "unit has already attacked this turn"),
+        }
+    }
+}
+
+impl std::error::Error for CombatError {}
+
+// Expected outcome of an attack, computed without rolling any dice
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct CombatPrediction {
+    // Chance for each point of attacker health to inflict one point of damage
+    pub hit_chance: f64,
+    // Same for the defender's counterattack, 0 if it cannot fire back
+    pub counter_hit_chance: f64,
+    pub expected_defender_damage: f64,
+    pub expected_attacker_damage: f64,
+    pub defender_destroyed_chance: f64,
+    pub attacker_destroyed_chance: f64,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct CombatResult {
+    pub defender_damage: u32,
+    pub attacker_damage: u32,
+    pub counterattacked: bool,
+}
+
+impl CombatResult {
+    // Applies damage, ammo use and experience to the two units that fought
+    pub fn apply(&self, attacker: &mut Unit, defender: &mut Unit) {
+        attacker.ammo = attacker.ammo.saturating_sub(1);
+        attacker.has_attacked = true;
+        defender.health = defender.health.saturating_sub(self.defender_damage);
+        attacker.health = attacker.health.saturating_sub(self.attacker_damage);
+        if self.defender_damage > 0 {
+            attacker.experience = (attacker.experience + 1).min(Unit::MAX_EXPERIENCE);
+        }
+        if self.counterattacked {
+            defender.ammo = defender.ammo.saturating_sub(1);
+            if self.attacker_damage > 0 {
+                defender.experience = (defender.experience + 1).min(Unit::MAX_EXPERIENCE);
+            }
+        }
+    }
+}
+
+// Checks whether `attacker` may attack `defender` at all
+pub fn check_attack(attacker: &Combatant, defender: &Combatant) -> Result<(), CombatError> {
+    if attacker.unit.is_destroyed() || defender.unit.is_destroyed() {
+        return Err(CombatError::Destroyed);
+    }
+    if attacker.unit.owner == defender.unit.owner {
+        return Err(CombatError::SameOwner);
+    }
+    if attacker.unit.has_attacked {
+        return Err(CombatError::AlreadyAttacked);
+    }
+    if attacker.unit.ammo == 0 {
+        return Err(CombatError::NoAmmo);
+    }
+    if attacker
+        .unit_type
+        .attack
+        .get(defender.unit_type.target_class())
+        == 0
+    {
+        return Err(CombatError::CannotTarget);
+    }
+    let distance = distance(attacker, defender);
+    if !attacker.unit_type.in_range(distance) {
+        return Err(CombatError::OutOfRange { distance });
+    }
+    Ok(())
+}
+
+pub fn predict_combat(
+    attacker: &Combatant,
+    defender: &Combatant,
+) -> Result<CombatPrediction, CombatError> {
+    check_attack(attacker, defender)?;
+    let hit_chance = chance_to_hit(attacker, defender);
+    let counter_hit_chance = if can_counter(attacker, defender) {
+        chance_to_hit(defender, attacker)
+    } else {
+        0.0
+    };
+
+    let attacker_health = attacker.unit.health;
+    let defender_health = defender.unit.health;
+    let mut prediction = CombatPrediction {
+        hit_chance,
+        counter_hit_chance,
+        expected_defender_damage: 0.0,
+        expected_attacker_damage: 0.0,
+        defender_destroyed_chance: 0.0,
+        attacker_destroyed_chance: 0.0,
+    };
+    // Enumerate every number of hits the attack can score, then the counterattack
+    // the remaining defender health can fire back
+    for (hits, p_hits) in binomial(attacker_health, hit_chance)
+        .into_iter()
+        .enumerate()
+    {
+        let damage = (hits as u32).min(defender_health);
+        prediction.expected_defender_damage += p_hits * f64::from(damage);
+        if damage == defender_health {
+            prediction.defender_destroyed_chance += p_hits;
+            continue;
+        }
+        let remaining = defender_health - damage;
+        for (counter_hits, p_counter) in binomial(remaining, counter_hit_chance)
+            .into_iter()
+            .enumerate()
+        {
+            let counter_damage = (counter_hits as u32).min(attacker_health);
+            prediction.expected_attacker_damage += p_hits * p_counter * f64::from(counter_damage);
+            if counter_damage == attacker_health {
+                prediction.attacker_destroyed_chance += p_hits * p_counter;
+            }
+        }
+    }
+    Ok(prediction)
+}
+
+// Resolves an attack. The outcome only depends on the inputs and the state of `rng`,
+// so the same seed always produces the same result.
+pub fn resolve_combat(
+    attacker: &Combatant,
+    defender: &Combatant,
+    rng: &mut SeededRng,
+) -> Result<CombatResult, CombatError> {
+    check_attack(attacker, defender)?;
+
+    let hit_chance = chance_to_hit(attacker, defender);
+    let hits = (0..attacker.unit.health)
+        .filter(|_| rng.chance(hit_chance))
+        .count() as u32;
+    let defender_damage = hits.min(defender.unit.health);
+
+    let remaining = defender.unit.health - defender_damage;
+    let counterattacked = remaining > 0 && can_counter(attacker, defender);
+    let attacker_damage = if counterattacked {
+        let counter_hit_chance = chance_to_hit(defender, attacker);
+        let counter_hits = (0..remaining)
+            .filter(|_| rng.chance(counter_hit_chance))
+            .count() as u32;
+        counter_hits.min(attacker.unit.health)
+    } else {
+        0
+    };
+
+    Ok(CombatResult {
+        defender_damage,
+        attacker_damage,
+        counterattacked,
+    })
+}
+
+fn distance(a: &Combatant, b: &Combatant) -> u32 {
+    a.unit.position.unsigned_distance_to(b.unit.position)
+}
+
+fn can_counter(attacker: &Combatant, defender: &Combatant) -> bool {
+    defender.unit.ammo > 0
+        && defender
+            .unit_type
+            .attack
+            .get(attacker.unit_type.target_class())
+            > 0
+        && defender.unit_type.in_range(distance(attacker, defender))
+}
+
+fn chance_to_hit(shooter: &Combatant, target: &Combatant) -> f64 {
+    let attack = f64::from(
+        shooter
+            .unit_type
+            .attack
+            .get(target.unit_type.target_class()),
+    ) * experience_multiplier(shooter.unit);
+    let terrain_bonus = match target.unit_type.target_class() {
+        TargetClass::Ground => terrain_defence_bonus(target.terrain),
+        _ => 0.0,
+    };
+    let defence = f64::from(
+        target
+            .unit_type
+            .defence
+            .get(shooter.unit_type.target_class()),
+    ) * (1.0 + terrain_bonus)
+        * experience_multiplier(target.unit);
+    if attack + defence == 0.0 {
+        0.0
+    } else {
+        attack / (attack + defence)
+    }
+}
+
+fn experience_multiplier(unit: &Unit) -> f64 {
+    1.0 + EXPERIENCE_BONUS * f64::from(unit.experience)
+}
+
+// Probability of 0..=n successes in n trials
+fn binomial(n: u32, p: f64) -> Vec<f64> {
+    let mut probabilities = vec![0.0; n as usize + 1];
+    probabilities[0] = 1.0;
+    for trial in 1..=n as usize {
+        for k in (1..=trial).rev() {
+            probabilities[k] = probabilities[k] * (1.0 - p) + probabilities[k - 1] * p;
+        }
+        probabilities[0] *= 1.0 - p;
+    }
+    probabilities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Hex;
+    use crate::pathfinding::MovementClass;
+    use crate::player::PlayerId;
+    use crate::unit::{TargetValues, UnitId, UnitTypeId};
+    use rstest::rstest;
+
+    fn unit_type(
+        id: &str,
+        movement_class: MovementClass,
+        min_range: u32,
+        max_range: u32,
+    ) -> UnitType {
+        UnitType {
+            id: UnitTypeId(id.to_owned()),
+            name: id.to_owned(),
+            movement_class,
+            movement_points: 3,
+            attack: TargetValues {
+                ground: 6,
+                naval: 0,
+                air: 0,
+            },
+            defence: TargetValues {
+                ground: 4,
+                naval: 4,
+                air: 4,
+            },
+            min_range,
+            max_range,
+            ammo: 5,
+            fuel: None,
+            vision: 2,
+            cost: 0,
+            transport: None,
+            can_capture: false,
+        }
+    }
+
+    fn tank() -> UnitType {
+        unit_type("tank", MovementClass::Tracked, 1, 1)
+    }
+
+    fn artillery() -> UnitType {
+        unit_type("artillery", MovementClass::Tracked, 2, 3)
+    }
+
+    fn unit(unit_type: &UnitType, owner: u8, position: Hex) -> Unit {
+        Unit::new(UnitId(owner.into()), unit_type, PlayerId(owner), position)
+    }
+
+    #[test]
+    fn test_same_seed_same_outcome() {
+        let tank = tank();
+        let attacker = unit(&tank, 1, Hex::new(0, 0));
+        let defender = unit(&tank, 2, Hex::new(1, 0));
+        let a = Combatant {
+            unit: &attacker,
+            unit_type: &tank,
+            terrain: Terrain::Plains,
+        };
+        let d = Combatant {
+            unit: &defender,
+            unit_type: &tank,
+            terrain: Terrain::Hills,
+        };
+
+        for seed in 0..50 {
+            let first = resolve_combat(&a, &d, &mut SeededRng::new(seed)).unwrap();
+            let second = resolve_combat(&a, &d, &mut SeededRng::new(seed)).unwrap();
+            assert_eq!(first, second);
+        }
+    }
+
+    #[rstest]
+    #[case(Terrain::Plains)]
+    #[case(Terrain::Hills)]
+    #[case(Terrain::Mountains)]
+    fn test_prediction_matches_average_outcome(#[case] terrain: Terrain) {
+        let tank = tank();
+        let attacker = unit(&tank, 1, Hex::new(0, 0));
+        let defender = unit(&tank, 2, Hex::new(1, 0));
+        let a = Combatant {
+            unit: &attacker,
+            unit_type: &tank,
+            terrain: Terrain::Plains,
+        };
+        let d = Combatant {
+            unit: &defender,
+            unit_type: &tank,
+            terrain,
+        };
+
+        let prediction = predict_combat(&a, &d).unwrap();
+        let runs = 4000;
+        let mut rng = SeededRng::new(1234);
+        let (defender_total, attacker_total) = (0..runs)
+            .map(|_| resolve_combat(&a, &d, &mut rng).unwrap())
+            .fold((0, 0), |(d, a), result| {
+                (d + result.defender_damage, a + result.attacker_damage)
+            });
+
+        let defender_average = f64::from(defender_total) / f64::from(runs);
+        let attacker_average = f64::from(attacker_total) / f64::from(runs);
+        assert!((defender_average - prediction.expected_defender_damage).abs() < 0.1);
+        assert!((attacker_average - prediction.expected_attacker_damage).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_terrain_and_experience_change_odds() {
+        let tank = tank();
+        let attacker = unit(&tank, 1, Hex::new(0, 0));
+        let mut defender = unit(&tank, 2, Hex::new(1, 0));
+        let a = Combatant {
+            unit: &attacker,
+            unit_type: &tank,
+            terrain: Terrain::Plains,
+        };
+        let on = |terrain, defender: &Unit| {
+            let d = Combatant {
+                unit: defender,
+                unit_type: &tank,
+                terrain,
+            };
+            predict_combat(&a, &d).unwrap().expected_defender_damage
+        };
+
+        let plains = on(Terrain::Plains, &defender);
+        let hills = on(Terrain::Hills, &defender);
+        let mountains = on(Terrain::Mountains, &defender);
+        assert!(plains > hills && hills > mountains);
+
+        defender.experience = 3;
+        assert!(on(Terrain::Plains, &defender) < plains);
+    }
+
+    #[test]
+    fn test_no_counterattack_out_of_defender_range() {
+        let tank = tank();
+        let artillery = artillery();
+        let attacker = unit(&tank, 1, Hex::new(0, 0));
+        let defender = unit(&artillery, 2, Hex::new(1, 0));
+        let a = Combatant {
+            unit: &attacker,
+            unit_type: &tank,
+            terrain: Terrain::Plains,
+        };
+        let d = Combatant {
+            unit: &defender,
+            unit_type: &artillery,
+            terrain: Terrain::Plains,
+        };
+
+        let prediction = predict_combat(&a, &d).unwrap();
+        let result = resolve_combat(&a, &d, &mut SeededRng::new(3)).unwrap();
+
+        assert_eq!(prediction.counter_hit_chance, 0.0);
+        assert_eq!(prediction.expected_attacker_damage, 0.0);
+        assert!(!result.counterattacked);
+        assert_eq!(result.attacker_damage, 0);
+    }
+
+    #[rstest]
+    #[case(Hex::new(2, 0), 1, 2, false, Err(CombatError::OutOfRange { distance: 2 }))]
+    #[case(Hex::new(1, 0), 1, 1, false, Err(CombatError::SameOwner))]
+    #[case(Hex::new(1, 0), 1, 2, true, Err(CombatError::AlreadyAttacked))]
+    #[case(Hex::new(1, 0), 1, 2, false, Ok(()))]
+    fn test_check_attack(
+        #[case] defender_position: Hex,
+        #[case] attacker_owner: u8,
+        #[case] defender_owner: u8,
+        #[case] has_attacked: bool,
+        #[case] expected: Result<(), CombatError>,
+    ) {
+        let tank = tank();
+        let mut attacker = unit(&tank, attacker_owner, Hex::new(0, 0));
+        attacker.has_attacked = has_attacked;
+        let defender = unit(&tank, defender_owner, defender_position);
+        let a = Combatant {
+            unit: &attacker,
+            unit_type: &tank,
+            terrain: Terrain::Plains,
+        };
+        let d = Combatant {
+            unit: &defender,
+            unit_type: &tank,
+            terrain: Terrain::Plains,
+        };
+        assert_eq!(check_attack(&a, &d), expected);
+    }
+
+    #[test]
+    fn test_apply_result() {
+        let tank = tank();
+        let mut attacker = unit(&tank, 1, Hex::new(0, 0));
+        let mut defender = unit(&tank, 2, Hex::new(1, 0));
+        let result = CombatResult {
+            defender_damage: 4,
+            attacker_damage: 2,
+            counterattacked: true,
+        };
+
+        result.apply(&mut attacker, &mut defender);
+
+        assert_eq!(defender.health, Unit::MAX_HEALTH - 4);
+        assert_eq!(attacker.health, Unit::MAX_HEALTH - 2);
+        assert_eq!(attacker.ammo, tank.ammo - 1);
+        assert_eq!(defender.ammo, tank.ammo - 1);
+        assert_eq!(attacker.experience, 1);
+        assert_eq!(defender.experience, 1);
+        assert!(attacker.has_attacked);
+    }
+
+    #[test]
+    fn test_apply_more_damage_than_health() {
+        let tank = tank();
+        let mut attacker = unit(&tank, 1, Hex::new(0, 0));
+        let mut defender = unit(&tank, 2, Hex::new(1, 0));
+        let result = CombatResult {
+            defender_damage: Unit::MAX_HEALTH + 1,
+            attacker_damage: Unit::MAX_HEALTH + 1,
+            counterattacked: true,
+        };
+
+        result.apply(&mut attacker, &mut defender);
+
+        assert!(defender.is_destroyed());
+        assert!(attacker.is_destroyed());
+    }
+
+    #[test]
+    fn test_binomial_sums_to_one() {
+        let sut = binomial(10, 0.3);
+        assert_eq!(sut.len(), 11);
+        assert!((sut.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!((sut[0] - 0.7f64.powi(10)).abs() < 1e-12);
+    }
+}
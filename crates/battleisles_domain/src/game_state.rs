@@ -0,0 +1,895 @@
+use crate::building::{Building, BuildingKind};
+use crate::map::{Hex, Map};
+use crate::pathfinding::MovementClass;
+use crate::player::{Player, PlayerId};
+use crate::unit::{Unit, UnitCatalogue, UnitId, UnitType, UnitTypeId};
+use crate::visibility::{Observer, PlayerVisibility};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+// Each player's turn is a movement phase followed by an attack phase
+#[derive(PartialEq, Clone, Debug, Copy, Eq, Hash, Serialize, Deserialize)]
+pub enum Phase {
+    Movement,
+    Attack,
+}
+
+// Things that happened during a state transition, in the order they happened
+#[derive(PartialEq, Clone, Debug)]
+pub enum GameEvent {
+    TurnStarted {
+        player: PlayerId,
+        turn: u32,
+    },
+    PhaseStarted {
+        player: PlayerId,
+        phase: Phase,
+    },
+    BuildingCaptured {
+        position: Hex,
+        kind: BuildingKind,
+        player: PlayerId,
+        previous_owner: Option<PlayerId>,
+    },
+    EnergyCollected {
+        player: PlayerId,
+        amount: u32,
+        total: u32,
+    },
+    UnitProduced {
+        unit: UnitId,
+        unit_type: UnitTypeId,
+        player: PlayerId,
+        position: Hex,
+    },
+    // A unit standing on a friendly building was repaired by `repaired` health points
+    // and/or had its ammo and fuel refilled at the start of its owner's turn
+    UnitServiced {
+        unit: UnitId,
+        repaired: u32,
+        resupplied: bool,
+    },
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum CaptureError {
+    NoSuchUnit(UnitId),
+    // The unit does not belong to the player whose turn it is
+    NotOwned,
+    // The unit has already attacked or captured this turn
+    AlreadyActed,
+    // The unit's type is not allowed to capture buildings
+    CannotCapture,
+    NoBuilding,
+    AlreadyOwned,
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::NoSuchUnit(id) => write!(f, "no unit with id {}", id.0),
+            CaptureError::NotOwned => write!(f, "the unit belongs to another player"),
+            CaptureError::AlreadyActed => write!(f, "the unit has already acted this turn"),
+            CaptureError::CannotCapture => write!(f, "this unit cannot capture buildings"),
+            CaptureError::NoBuilding => write!(f, "there is no building here"),
+            CaptureError::AlreadyOwned => write!(f, "the building already belongs to this player"),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum ProductionError {
+    NoBuilding,
+    // The building does not belong to the player whose turn it is
+    NotOwned,
+    UnknownUnitType(UnitTypeId),
+    // The building cannot produce units of this movement class
+    CannotProduce,
+    InsufficientEnergy { cost: u32, available: u32 },
+    Full { capacity: usize },
+}
+
+impl fmt::Display for ProductionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProductionError::NoBuilding => write!(f, "there is no building here"),
+            ProductionError::NotOwned => write!(f, "the building belongs to another player"),
+            ProductionError::UnknownUnitType(id) => write!(f, "unknown unit type {id}"),
+            ProductionError::CannotProduce => write!(f, "the building cannot produce this unit"),
+            ProductionError::InsufficientEnergy { cost, available } => {
+                write!(f, "not enough energy ({available} of {cost})")
+            }
+            ProductionError::Full { capacity } => {
+                write!(f, "the building is full ({capacity} units)")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProductionError {}
+
+#[derive(Clone, Debug)]
+pub struct GameState {
+    pub map: Map,
+    catalogue: UnitCatalogue,
+    players: Vec<Player>,
+    units: Vec<Unit>,
+    next_unit_id: u32,
+    // Index into `players`
+    current_player: usize,
+    turn: u32,
+    phase: Phase,
+    visibility: HashMap<PlayerId, PlayerVisibility>,
+}
+
+impl GameState {
+    // Starts at turn 1 with the first player's movement phase, with that player's income
+    // for the turn already collected. Panics if `players` is empty.
+    pub fn new(map: Map, players: Vec<Player>, catalogue: UnitCatalogue) -> Self {
+        assert!(!players.is_empty(), "a game needs at least one player");
+        let mut state = GameState {
+            map,
+            catalogue,
+            players,
+            units: Vec::new(),
+            next_unit_id: 0,
+            current_player: 0,
+            turn: 1,
+            phase: Phase::Movement,
+            visibility: HashMap::new(),
+        };
+        let first = state.current_player().id;
+        state.collect_income(first);
+        state.update_visibility();
+        state
+    }
+
+    pub fn catalogue(&self) -> &UnitCatalogue {
+        &self.catalogue
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    pub fn player(&self, id: PlayerId) -> Option<&Player> {
+        self.players.iter().find(|player| player.id == id)
+    }
+
+    pub fn current_player(&self) -> &Player {
+        &self.players[self.current_player]
+    }
+
+    // Energy the player collects per turn from the buildings they own
+    pub fn income(&self, player: PlayerId) -> u32 {
+        self.buildings_of(player)
+            .map(|(_, building)| building.kind.income())
+            .sum()
+    }
+
+    // Number of the current round; it increases once every player has had their turn
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn units(&self) -> &[Unit] {
+        &self.units
+    }
+
+    pub fn unit(&self, id: UnitId) -> Option<&Unit> {
+        self.units.iter().find(|unit| unit.id == id)
+    }
+
+    pub fn unit_mut(&mut self, id: UnitId) -> Option<&mut Unit> {
+        self.units.iter_mut().find(|unit| unit.id == id)
+    }
+
+    pub fn unit_at(&self, hex: Hex) -> Option<&Unit> {
+        self.units.iter().find(|unit| unit.position == hex)
+    }
+
+    pub fn units_of(&self, player: PlayerId) -> impl Iterator<Item = &Unit> {
+        self.units.iter().filter(move |unit| unit.owner == player)
+    }
+
+    // Every building on the map with its position
+    pub fn buildings(&self) -> impl Iterator<Item = (Hex, Building)> + '_ {
+        self.map
+            .tiles()
+            .iter()
+            .filter_map(|tile| tile.building.map(|building| (tile.position(), building)))
+    }
+
+    pub fn buildings_of(&self, player: PlayerId) -> impl Iterator<Item = (Hex, Building)> + '_ {
+        self.buildings()
+            .filter(move |(_, building)| building.owner == Some(player))
+    }
+
+    pub fn add_unit(&mut self, unit_type: &UnitType, owner: PlayerId, position: Hex) -> UnitId {
+        let id = UnitId(self.next_unit_id);
+        self.next_unit_id += 1;
+        self.units.push(Unit::new(id, unit_type, owner, position));
+        self.update_visibility();
+        id
+    }
+
+    pub fn remove_unit(&mut self, id: UnitId) -> Option<Unit> {
+        let index = self.units.iter().position(|unit| unit.id == id)?;
+        let unit = self.units.remove(index);
+        self.update_visibility();
+        Some(unit)
+    }
+
+    // What the player sees now and has seen before
+    pub fn visibility(&self, player: PlayerId) -> Option<&PlayerVisibility> {
+        self.visibility.get(&player)
+    }
+
+    // Recomputes every player's sight. State transitions do this themselves, call it after
+    // moving units or changing buildings directly through `unit_mut` or `map`.
+    pub fn update_visibility(&mut self) {
+        for player in self.players.iter().map(|player| player.id) {
+            let units = self.units_of(player).filter_map(|unit| {
+                let unit_type = self.catalogue.get(&unit.unit_type)?;
+                Some(Observer {
+                    position: unit.position,
+                    vision: unit_type.vision,
+                    airborne: unit_type.movement_class == MovementClass::Air,
+                })
+            });
+            let buildings = self
+                .buildings_of(player)
+                .map(|(position, building)| Observer {
+                    position,
+                    vision: building.kind.vision(),
+                    airborne: false,
+                });
+            let observers = units.chain(buildings).collect::<Vec<_>>();
+            self.visibility
+                .entry(player)
+                .or_default()
+                .update(&self.map, observers);
+        }
+    }
+
+    // Hands the building under the unit over to the unit's owner. Like an attack this is
+    // the unit's action for the turn, after which it can neither move nor attack.
+    pub fn capture_building(&mut self, id: UnitId) -> Result<Vec<GameEvent>, CaptureError> {
+        let unit = self.unit(id).ok_or(CaptureError::NoSuchUnit(id))?;
+        if unit.owner != self.current_player().id {
+            return Err(CaptureError::NotOwned);
+        }
+        if unit.has_attacked {
+            return Err(CaptureError::AlreadyActed);
+        }
+        let can_capture = self
+            .catalogue
+            .get(&unit.unit_type)
+            .is_some_and(|unit_type| unit_type.can_capture);
+        if !can_capture {
+            return Err(CaptureError::CannotCapture);
+        }
+        let (position, player) = (unit.position, unit.owner);
+        let building = self
+            .map
+            .tile_at_mut(position)
+            .and_then(|tile| tile.building.as_mut())
+            .ok_or(CaptureError::NoBuilding)?;
+        if building.owner == Some(player) {
+            return Err(CaptureError::AlreadyOwned);
+        }
+
+        let previous_owner = building.owner.replace(player);
+        let kind = building.kind;
+        let unit = self.unit_mut(id).expect("unit was checked above");
+        unit.has_moved = true;
+        unit.has_attacked = true;
+        self.update_visibility();
+        Ok(vec![GameEvent::BuildingCaptured {
+            position,
+            kind,
+            player,
+            previous_owner,
+        }])
+    }
+
+    // Validates that the current player can produce `unit_type` at the building at `at`
+    pub fn check_production(
+        &self,
+        at: Hex,
+        unit_type: &UnitTypeId,
+    ) -> Result<&UnitType, ProductionError> {
+        let player = self.current_player();
+        let building = self
+            .map
+            .tile_at(at)
+            .and_then(|tile| tile.building)
+            .ok_or(ProductionError::NoBuilding)?;
+        if building.owner != Some(player.id) {
+            return Err(ProductionError::NotOwned);
+        }
+        let unit_type = self
+            .catalogue
+            .get(unit_type)
+            .ok_or_else(|| ProductionError::UnknownUnitType(unit_type.clone()))?;
+        if !building.kind.produces(unit_type.movement_class) {
+            return Err(ProductionError::CannotProduce);
+        }
+        if player.energy < unit_type.cost {
+            return Err(ProductionError::InsufficientEnergy {
+                cost: unit_type.cost,
+                available: player.energy,
+            });
+        }
+        let capacity = building.kind.capacity();
+        if self.units.iter().filter(|unit| unit.position == at).count() >= capacity {
+            return Err(ProductionError::Full { capacity });
+        }
+        Ok(unit_type)
+    }
+
+    // Builds a unit inside the current player's building at `at`, paying its cost. The new
+    // unit cannot move or attack until its owner's next turn.
+    pub fn produce_unit(
+        &mut self,
+        at: Hex,
+        unit_type: &UnitTypeId,
+    ) -> Result<Vec<GameEvent>, ProductionError> {
+        let unit_type = self.check_production(at, unit_type)?.clone();
+        let player = self.current_player().id;
+        self.players[self.current_player].energy -= unit_type.cost;
+        let id = self.add_unit(&unit_type, player, at);
+        let unit = self.unit_mut(id).expect("unit was just added");
+        unit.has_moved = true;
+        unit.has_attacked = true;
+
+        Ok(vec![GameEvent::UnitProduced {
+            unit: id,
+            unit_type: unit_type.id,
+            player,
+            position: at,
+        }])
+    }
+
+    // Moves from the movement to the attack phase, or on to the next player's turn
+    pub fn end_phase(&mut self) -> Vec<GameEvent> {
+        match self.phase {
+            Phase::Movement => {
+                self.phase = Phase::Attack;
+                vec![GameEvent::PhaseStarted {
+                    player: self.current_player().id,
+                    phase: Phase::Attack,
+                }]
+            }
+            Phase::Attack => self.end_turn(),
+        }
+    }
+
+    // Skips any remaining phases and hands over to the next player
+    pub fn end_turn(&mut self) -> Vec<GameEvent> {
+        self.current_player += 1;
+        if self.current_player == self.players.len() {
+            self.current_player = 0;
+            self.turn += 1;
+        }
+        self.phase = Phase::Movement;
+
+        let player = self.current_player().id;
+        for unit in self.units.iter_mut().filter(|unit| unit.owner == player) {
+            unit.has_moved = false;
+            unit.has_attacked = false;
+        }
+
+        let mut events = vec![GameEvent::TurnStarted {
+            player,
+            turn: self.turn,
+        }];
+        events.extend(self.collect_income(player));
+        events.extend(self.service_units(player));
+        self.update_visibility();
+        events.push(GameEvent::PhaseStarted {
+            player,
+            phase: Phase::Movement,
+        });
+        events
+    }
+
+    fn collect_income(&mut self, player: PlayerId) -> Option<GameEvent> {
+        let amount = self.income(player);
+        let player = self.players.iter_mut().find(|p| p.id == player)?;
+        if amount == 0 {
+            return None;
+        }
+        player.energy += amount;
+        Some(GameEvent::EnergyCollected {
+            player: player.id,
+            amount,
+            total: player.energy,
+        })
+    }
+
+    // Repairs and resupplies the player's units that stand on their own buildings
+    fn service_units(&mut self, player: PlayerId) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        for unit in self.units.iter_mut().filter(|unit| unit.owner == player) {
+            let Some(building) = self
+                .map
+                .tile_at(unit.position)
+                .and_then(|tile| tile.building)
+            else {
+                continue;
+            };
+            let Some(unit_type) = self.catalogue.get(&unit.unit_type) else {
+                continue;
+            };
+            if building.owner != Some(player) {
+                continue;
+            }
+
+            let class = unit_type.movement_class;
+            let mut repaired = 0;
+            if building.kind.repairs(class) {
+                repaired = Building::REPAIR_PER_TURN.min(Unit::MAX_HEALTH - unit.health);
+                unit.health += repaired;
+            }
+            let mut resupplied = false;
+            if building.kind.resupplies(class)
+                && (unit.ammo != unit_type.ammo || unit.fuel != unit_type.fuel)
+            {
+                unit.ammo = unit_type.ammo;
+                unit.fuel = unit_type.fuel;
+                resupplied = true;
+            }
+            if repaired > 0 || resupplied {
+                events.push(GameEvent::UnitServiced {
+                    unit: unit.id,
+                    repaired,
+                    resupplied,
+                });
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::Faction;
+    use crate::unit::UnitTypeId;
+    use rstest::rstest;
+
+    fn catalogue() -> UnitCatalogue {
+        UnitCatalogue::from_ron_str(
+            "(unit_types: [(id: \"infantry\", name: \"Infantry\", movement_class: Land, \
+             movement_points: 3, attack: (ground: 4, naval: 0, air: 1), \
+             defence: (ground: 3, naval: 3, air: 2), min_range: 1, max_range: 1, \
+             ammo: 8, fuel: None, vision: 2, cost: 100, transport: None, can_capture: true), \
+             (id: \"fighter\", name: \"Fighter\", movement_class: Air, \
+             movement_points: 9, attack: (ground: 2, naval: 2, air: 7), \
+             defence: (ground: 3, naval: 3, air: 6), min_range: 1, max_range: 1, \
+             ammo: 6, fuel: Some(40), vision: 3, cost: 400, transport: None, \
+             can_capture: false)])",
+        )
+        .unwrap()
+    }
+
+    fn two_player_game() -> GameState {
+        GameState::new(
+            Map::new(5, 5),
+            vec![
+                Player::new(PlayerId(1), "Blue", Faction::Drull),
+                Player::new(PlayerId(2), "Red", Faction::Kai),
+            ],
+            catalogue(),
+        )
+    }
+
+    fn add(sut: &mut GameState, unit_type: &str, owner: u8, position: Hex) -> UnitId {
+        let unit_type = sut
+            .catalogue()
+            .get(&UnitTypeId(unit_type.to_owned()))
+            .unwrap()
+            .clone();
+        sut.add_unit(&unit_type, PlayerId(owner), position)
+    }
+
+    fn place(sut: &mut GameState, kind: BuildingKind, owner: Option<u8>, position: Hex) {
+        sut.map.tile_at_mut(position).unwrap().building =
+            Some(Building::new(kind, owner.map(PlayerId)));
+    }
+
+    #[rstest]
+    #[case(0, 1, 1, Phase::Movement)]
+    #[case(1, 1, 1, Phase::Attack)]
+    #[case(2, 1, 2, Phase::Movement)]
+    #[case(3, 1, 2, Phase::Attack)]
+    #[case(4, 2, 1, Phase::Movement)]
+    #[case(7, 2, 2, Phase::Attack)]
+    fn test_end_phase(
+        #[case] phases_ended: usize,
+        #[case] expected_turn: u32,
+        #[case] expected_player: u8,
+        #[case] expected_phase: Phase,
+    ) {
+        let mut sut = two_player_game();
+        for _ in 0..phases_ended {
+            sut.end_phase();
+        }
+        assert_eq!(sut.turn(), expected_turn);
+        assert_eq!(sut.current_player().id, PlayerId(expected_player));
+        assert_eq!(sut.phase(), expected_phase);
+    }
+
+    #[test]
+    fn test_end_phase_events() {
+        let mut sut = two_player_game();
+
+        assert_eq!(
+            sut.end_phase(),
+            vec![GameEvent::PhaseStarted {
+                player: PlayerId(1),
+                phase: Phase::Attack
+            }]
+        );
+        assert_eq!(
+            sut.end_phase(),
+            vec![
+                GameEvent::TurnStarted {
+                    player: PlayerId(2),
+                    turn: 1
+                },
+                GameEvent::PhaseStarted {
+                    player: PlayerId(2),
+                    phase: Phase::Movement
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_end_turn_skips_attack_phase() {
+        let mut sut = two_player_game();
+        sut.end_turn();
+        sut.end_turn();
+        assert_eq!(sut.turn(), 2);
+        assert_eq!(sut.current_player().id, PlayerId(1));
+        assert_eq!(sut.phase(), Phase::Movement);
+    }
+
+    #[test]
+    fn test_end_turn_resets_flags_of_next_player() {
+        let mut sut = two_player_game();
+        let blue = add(&mut sut, "infantry", 1, Hex::new(0, 0));
+        let red = add(&mut sut, "infantry", 2, Hex::new(1, 0));
+        for id in [blue, red] {
+            let unit = sut.unit_mut(id).unwrap();
+            unit.has_moved = true;
+            unit.has_attacked = true;
+        }
+
+        sut.end_turn();
+
+        assert!(sut.unit(blue).unwrap().has_moved);
+        assert!(!sut.unit(red).unwrap().has_moved);
+        assert!(!sut.unit(red).unwrap().has_attacked);
+        assert_eq!(sut.unit_at(Hex::new(1, 0)).map(|unit| unit.id), Some(red));
+        assert_eq!(sut.units_of(PlayerId(1)).count(), 1);
+        assert!(sut.remove_unit(blue).is_some());
+        assert!(sut.unit(blue).is_none());
+    }
+
+    #[rstest]
+    #[case("infantry", Some(BuildingKind::Factory), None, Ok(None))]
+    #[case(
+        "infantry",
+        Some(BuildingKind::Headquarters),
+        Some(2),
+        Ok(Some(PlayerId(2)))
+    )]
+    #[case(
+        "infantry",
+        Some(BuildingKind::Depot),
+        Some(1),
+        Err(CaptureError::AlreadyOwned)
+    )]
+    #[case("infantry", None, None, Err(CaptureError::NoBuilding))]
+    #[case(
+        "fighter",
+        Some(BuildingKind::Airfield),
+        None,
+        Err(CaptureError::CannotCapture)
+    )]
+    fn test_capture_building(
+        #[case] unit_type: &str,
+        #[case] kind: Option<BuildingKind>,
+        #[case] owner: Option<u8>,
+        #[case] expected: Result<Option<PlayerId>, CaptureError>,
+    ) {
+        let mut sut = two_player_game();
+        let position = Hex::new(1, 1);
+        if let Some(kind) = kind {
+            place(&mut sut, kind, owner, position);
+        }
+        let unit = add(&mut sut, unit_type, 1, position);
+
+        let result = sut.capture_building(unit);
+
+        match expected {
+            Ok(previous_owner) => {
+                assert_eq!(
+                    result,
+                    Ok(vec![GameEvent::BuildingCaptured {
+                        position,
+                        kind: kind.unwrap(),
+                        player: PlayerId(1),
+                        previous_owner,
+                    }])
+                );
+                assert_eq!(sut.buildings_of(PlayerId(1)).count(), 1);
+                let unit = sut.unit(unit).unwrap();
+                assert!(unit.has_moved && unit.has_attacked);
+            }
+            Err(e) => {
+                assert_eq!(result, Err(e));
+                let tile = sut.map.tile_at(position).unwrap();
+                assert_eq!(
+                    tile.building.and_then(|building| building.owner),
+                    owner.map(PlayerId)
+                );
+            }
+        }
+    }
+
+    #[rstest]
+    // Player 2's unit during player 1's turn
+    #[case(2, false, CaptureError::NotOwned)]
+    #[case(1, true, CaptureError::AlreadyActed)]
+    fn test_capture_out_of_turn(
+        #[case] owner: u8,
+        #[case] has_attacked: bool,
+        #[case] expected: CaptureError,
+    ) {
+        let mut sut = two_player_game();
+        let position = Hex::new(1, 1);
+        place(&mut sut, BuildingKind::Factory, None, position);
+        let unit = add(&mut sut, "infantry", owner, position);
+        sut.unit_mut(unit).unwrap().has_attacked = has_attacked;
+
+        assert_eq!(sut.capture_building(unit), Err(expected));
+        let tile = sut.map.tile_at(position).unwrap();
+        assert_eq!(tile.building.and_then(|building| building.owner), None);
+    }
+
+    #[test]
+    fn test_capturing_twice_in_a_turn() {
+        let mut sut = two_player_game();
+        let position = Hex::new(1, 1);
+        place(&mut sut, BuildingKind::Factory, None, position);
+        let unit = add(&mut sut, "infantry", 1, position);
+
+        assert!(sut.capture_building(unit).is_ok());
+        // Lost to player 2 again, the unit has to wait for its next turn
+        place(&mut sut, BuildingKind::Factory, Some(2), position);
+        assert_eq!(sut.capture_building(unit), Err(CaptureError::AlreadyActed));
+    }
+
+    #[test]
+    fn test_turn_start_services_units_on_own_buildings() {
+        let mut sut = two_player_game();
+        place(&mut sut, BuildingKind::Depot, Some(2), Hex::new(0, 0));
+        place(
+            &mut sut,
+            BuildingKind::Headquarters,
+            Some(2),
+            Hex::new(1, 0),
+        );
+        place(&mut sut, BuildingKind::Airfield, Some(1), Hex::new(2, 0));
+        let on_depot = add(&mut sut, "fighter", 2, Hex::new(0, 0));
+        let on_hq = add(&mut sut, "infantry", 2, Hex::new(1, 0));
+        let on_enemy = add(&mut sut, "fighter", 2, Hex::new(2, 0));
+        for id in [on_depot, on_hq, on_enemy] {
+            let unit = sut.unit_mut(id).unwrap();
+            unit.health = 5;
+            unit.ammo = 0;
+            unit.fuel = unit.fuel.map(|_| 1);
+        }
+
+        let events = sut.end_turn();
+
+        assert_eq!(
+            events[2..4],
+            [
+                GameEvent::UnitServiced {
+                    unit: on_depot,
+                    repaired: 0,
+                    resupplied: true
+                },
+                GameEvent::UnitServiced {
+                    unit: on_hq,
+                    repaired: Building::REPAIR_PER_TURN,
+                    resupplied: true
+                },
+            ]
+        );
+        assert_eq!(events.len(), 5);
+        assert_eq!(sut.unit(on_depot).unwrap().fuel, Some(40));
+        assert_eq!(
+            sut.unit(on_hq).unwrap().health,
+            5 + Building::REPAIR_PER_TURN
+        );
+        assert_eq!(sut.unit(on_enemy).unwrap().ammo, 0);
+    }
+
+    #[test]
+    fn test_income_is_collected_at_turn_start() {
+        let mut map = Map::new(5, 5);
+        let hq = Building::new(BuildingKind::Headquarters, Some(PlayerId(1)));
+        map.tile_at_mut(Hex::new(0, 0)).unwrap().building = Some(hq);
+        let depot = Building::new(BuildingKind::Depot, Some(PlayerId(2)));
+        map.tile_at_mut(Hex::new(1, 0)).unwrap().building = Some(depot);
+        let mut sut = GameState::new(
+            map,
+            vec![
+                Player::new(PlayerId(1), "Blue", Faction::Drull),
+                Player::new(PlayerId(2), "Red", Faction::Kai),
+            ],
+            catalogue(),
+        );
+        // The first player's income for turn 1 is collected up front
+        assert_eq!(sut.current_player().energy, 200);
+
+        let events = sut.end_turn();
+        assert_eq!(
+            events[1],
+            GameEvent::EnergyCollected {
+                player: PlayerId(2),
+                amount: 50,
+                total: 50
+            }
+        );
+        sut.end_turn();
+        assert_eq!(sut.player(PlayerId(1)).unwrap().energy, 400);
+        assert_eq!(sut.income(PlayerId(2)), 50);
+    }
+
+    #[rstest]
+    #[case(BuildingKind::Factory, Some(1), "infantry", 100, 0, Ok(()))]
+    #[case(BuildingKind::Airfield, Some(1), "fighter", 400, 3, Ok(()))]
+    #[case(
+        BuildingKind::Factory,
+        None,
+        "infantry",
+        100,
+        0,
+        Err(ProductionError::NotOwned)
+    )]
+    #[case(
+        BuildingKind::Factory,
+        Some(2),
+        "infantry",
+        100,
+        0,
+        Err(ProductionError::NotOwned)
+    )]
+    #[case(
+        BuildingKind::Factory,
+        Some(1),
+        "fighter",
+        400,
+        0,
+        Err(ProductionError::CannotProduce)
+    )]
+    #[case(
+        BuildingKind::Depot,
+        Some(1),
+        "infantry",
+        100,
+        0,
+        Err(ProductionError::CannotProduce)
+    )]
+    #[case(
+        BuildingKind::Factory,
+        Some(1),
+        "infantry",
+        99,
+        0,
+        Err(ProductionError::InsufficientEnergy { cost: 100, available: 99 })
+    )]
+    #[case(BuildingKind::Airfield, Some(1), "fighter", 400, 4, Err(ProductionError::Full { capacity: 4 }))]
+    #[case(
+        BuildingKind::Factory,
+        Some(1),
+        "tank",
+        1000,
+        0,
+        Err(ProductionError::UnknownUnitType(UnitTypeId("tank".to_owned())))
+    )]
+    fn test_produce_unit(
+        #[case] kind: BuildingKind,
+        #[case] owner: Option<u8>,
+        #[case] unit_type: &str,
+        #[case] energy: u32,
+        #[case] units_inside: usize,
+        #[case] expected: Result<(), ProductionError>,
+    ) {
+        let mut sut = two_player_game();
+        let position = Hex::new(2, 2);
+        place(&mut sut, kind, owner, position);
+        for _ in 0..units_inside {
+            add(&mut sut, "fighter", 1, position);
+        }
+        sut.players[0].energy = energy;
+        let unit_type = UnitTypeId(unit_type.to_owned());
+
+        let result = sut.produce_unit(position, &unit_type);
+
+        match expected {
+            Ok(()) => {
+                let events = result.unwrap();
+                let GameEvent::UnitProduced { unit, .. } = events[0] else {
+                    panic!("unexpected event {:?}", events[0]);
+                };
+                let unit = sut.unit(unit).unwrap();
+                assert_eq!(unit.unit_type, unit_type);
+                assert_eq!(unit.position, position);
+                assert!(unit.has_moved && unit.has_attacked);
+                assert_eq!(sut.current_player().energy, 0);
+            }
+            Err(e) => {
+                assert_eq!(result, Err(e));
+                assert_eq!(sut.current_player().energy, energy);
+                assert_eq!(sut.units().len(), units_inside);
+            }
+        }
+    }
+
+    #[test]
+    fn test_produced_unit_can_act_next_turn() {
+        let mut sut = two_player_game();
+        place(&mut sut, BuildingKind::Factory, Some(1), Hex::new(0, 0));
+        sut.players[0].energy = 100;
+        let infantry = UnitTypeId("infantry".to_owned());
+        sut.produce_unit(Hex::new(0, 0), &infantry).unwrap();
+
+        sut.end_turn();
+        sut.end_turn();
+
+        let unit = &sut.units()[0];
+        assert!(!unit.has_moved && !unit.has_attacked);
+    }
+
+    #[test]
+    fn test_visibility_follows_units_and_buildings() {
+        let mut sut = two_player_game();
+        place(&mut sut, BuildingKind::Depot, Some(2), Hex::new(0, 4));
+        sut.update_visibility();
+        let blue = sut.visibility(PlayerId(1)).unwrap();
+        assert!(!blue.is_visible(Hex::new(0, 0)));
+        let red = sut.visibility(PlayerId(2)).unwrap();
+        assert!(red.is_visible(Hex::new(0, 3)));
+        assert!(!red.is_visible(Hex::new(0, 0)));
+
+        let infantry = add(&mut sut, "infantry", 1, Hex::new(0, 0));
+        assert!(sut
+            .visibility(PlayerId(1))
+            .unwrap()
+            .is_visible(Hex::new(2, 0)));
+        assert!(!sut
+            .visibility(PlayerId(1))
+            .unwrap()
+            .is_visible(Hex::new(3, 0)));
+
+        sut.remove_unit(infantry);
+        let blue = sut.visibility(PlayerId(1)).unwrap();
+        assert!(!blue.is_visible(Hex::new(0, 0)));
+        assert!(blue.was_seen(Hex::new(0, 0)));
+    }
+}
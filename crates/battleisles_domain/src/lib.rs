@@ -1 +1,13 @@
+pub mod building;
+pub mod combat;
+pub mod game_state;
+pub mod line_of_sight;
 pub mod map;
+pub mod map_file;
+pub mod map_generator;
+pub mod map_resize;
+pub mod pathfinding;
+pub mod player;
+pub mod rng;
+pub mod unit;
+pub mod visibility;
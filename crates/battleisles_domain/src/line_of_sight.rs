@@ -0,0 +1,119 @@
+use crate::map::{Elevation, Hex, Map, Tile};
+
+impl Map {
+    // Whether `to` can be seen from `from`. Sight is blocked by any tile on the hex line
+    // between them that is higher than both ends, so a hill hides what lies behind it from
+    // the plains but not from another hill. Flat land never blocks, not even between two
+    // water tiles. Lines running exactly along a hex edge are clear if either side is,
+    // which keeps the check symmetric. False if either end is off the map.
+    pub fn has_line_of_sight(&self, from: Hex, to: Hex) -> bool {
+        let (Some(start), Some(end)) = (self.tile_at(from), self.tile_at(to)) else {
+            return false;
+        };
+        let eye = start
+            .terrain
+            .elevation()
+            .max(end.terrain.elevation())
+            .max(Elevation::Plains);
+        let clear = |line: Vec<Hex>| {
+            line.iter()
+                .skip(1)
+                .take(line.len().saturating_sub(2))
+                .all(|hex| {
+                    self.tile_at(*hex)
+                        .is_none_or(|tile| tile.terrain.elevation() <= eye)
+                })
+        };
+        clear(from.line_to(to).collect()) || clear(to.line_to(from).collect())
+    }
+
+    // Tiles whose distance from `from` lies within `min..=max`, e.g. the hexes an
+    // artillery piece can target
+    pub fn tiles_in_range(
+        &self,
+        from: Hex,
+        min: u32,
+        max: u32,
+    ) -> impl Iterator<Item = &Tile> + '_ {
+        self.range(from, max)
+            .filter(move |tile| tile.position().unsigned_distance_to(from) >= min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::{Hex, Map, Terrain};
+    use rstest::rstest;
+
+    // A row of tiles built from one letter per terrain:
+    // p plains, h hills, m mountains, w deep water, s shallow water
+    fn row(terrain: &str) -> Map {
+        let mut map = Map::new(terrain.len() as u32, 1);
+        for (q, c) in terrain.chars().enumerate() {
+            map.tile_at_mut(Hex::new(q as i32, 0)).unwrap().terrain = match c {
+                'p' => Terrain::Plains,
+                'h' => Terrain::Hills,
+                'm' => Terrain::Mountains,
+                'w' => Terrain::DeepWater,
+                's' => Terrain::ShallowWater,
+                _ => panic!("unknown terrain {c}"),
+            };
+        }
+        map
+    }
+
+    #[rstest]
+    #[case("ppppp", 0, 4, true)]
+    #[case("pphpp", 0, 4, false)] // a hill hides the plains behind it
+    #[case("pphpp", 0, 2, true)] // but can be seen itself
+    #[case("hphph", 0, 4, true)] // hills see each other over a hill
+    #[case("hpmph", 0, 4, false)] // but not over a mountain
+    #[case("mpmpp", 0, 4, true)] // a mountain sees over another mountain
+    #[case("wswpw", 0, 4, true)] // neither water nor flat land blocks
+    #[case("wwhww", 0, 4, false)]
+    #[case("ppppp", 2, 2, true)]
+    #[case("ppppp", 0, 7, false)] // off the map
+    fn test_has_line_of_sight(
+        #[case] terrain: &str,
+        #[case] from: i32,
+        #[case] to: i32,
+        #[case] expected: bool,
+    ) {
+        let sut = row(terrain);
+        let (from, to) = (Hex::new(from, 0), Hex::new(to, 0));
+        assert_eq!(sut.has_line_of_sight(from, to), expected);
+        assert_eq!(sut.has_line_of_sight(to, from), expected);
+    }
+
+    #[test]
+    fn test_line_of_sight_along_a_hex_edge() {
+        // The line from (0,0) to (1,1) runs between (1,0) and (0,1); a single
+        // mountain on one side must not block it
+        let mut sut = Map::new(5, 5);
+        sut.tiles_mut()
+            .iter_mut()
+            .for_each(|tile| tile.terrain = Terrain::Plains);
+        sut.tile_at_mut(Hex::new(1, 0)).unwrap().terrain = Terrain::Mountains;
+        assert!(sut.has_line_of_sight(Hex::new(0, 0), Hex::new(1, 1)));
+
+        sut.tile_at_mut(Hex::new(0, 1)).unwrap().terrain = Terrain::Mountains;
+        assert!(!sut.has_line_of_sight(Hex::new(0, 0), Hex::new(1, 1)));
+    }
+
+    #[rstest]
+    #[case(0, 0, 1)]
+    #[case(1, 1, 6)]
+    #[case(2, 3, 30)]
+    #[case(0, 2, 19)]
+    #[case(3, 2, 0)]
+    fn test_tiles_in_range(#[case] min: u32, #[case] max: u32, #[case] expected_count: usize) {
+        let sut = Map::new(9, 9);
+        let center = sut.tile_at_offset(4, 4).unwrap().position();
+        let tiles = sut.tiles_in_range(center, min, max).collect::<Vec<_>>();
+        assert_eq!(tiles.len(), expected_count);
+        tiles.iter().for_each(|tile| {
+            let distance = tile.position().unsigned_distance_to(center);
+            assert!((min..=max).contains(&distance));
+        });
+    }
+}
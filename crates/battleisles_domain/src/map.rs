@@ -1,62 +1,325 @@
-use hexx::shapes;
-use hexx::Hex;
+use crate::building::Building;
 use hexx::HexLayout;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+pub use hexx::{Hex, HexOrientation};
 
 #[derive(PartialEq, Clone, Debug)]
 pub struct Tile {
-    position: Hex,
+    pub(crate) position: Hex,
     pub terrain: Terrain,
+    pub building: Option<Building>,
+}
+
+impl Tile {
+    pub fn position(&self) -> Hex {
+        self.position
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct Map {
     pub hex_size: f32,
-    layout: HexLayout,
-    pub tiles: Vec<Tile>,
+    pub(crate) layout: HexLayout,
+    // Tiles can be edited in place but not added or removed, which keeps `index` and
+    // `world_bounds` valid
+    pub(crate) tiles: Vec<Tile>,
+    index: HashMap<Hex, usize>,
+    // Columns and rows as given to `new`, see from_tiles for other maps
+    width: u32,
+    height: u32,
+    offset: OffsetConvention,
+    world_bounds: Option<WorldBounds>,
+    // Smallest and largest doubled coordinates of the tiles, see mirror_hex
+    doubled_bounds: Option<((i32, i32), (i32, i32))>,
 }
 
 impl Map {
+    // `width` x `height` pointy-top tiles with the odd rows trimmed, see MapShape
     pub fn new(width: u32, height: u32) -> Self {
-        let hex_size = 1.0;
-        let layout = HexLayout::pointy().with_hex_size(hex_size);
-        if width == 0 || height == 0 {
-            return Map {
-                hex_size,
-                layout,
-                tiles: Vec::new(),
-            };
-        }
+        Map::with_shape(MapShape::TrimmedRectangle { width, height }, HexOrientation::Pointy)
+    }
 
-        let q_min = 0_i32;
-        let q_max = width as i32 - 1;
-        let r_min = 0_i32;
-        let r_max = height as i32 - 1;
-        let w = width as i32;
-
-        // Generate equal rows with hexx, then drop the rightmost hex on odd rows.
-        // Resulting tile count: width*height - height/2 (e.g., 10*10 - 5 = 95).
-        let tiles = shapes::pointy_rectangle([q_min, q_max, r_min, r_max])
-            .map(Hex::from)
-            .filter(|h| {
-                let q = h.x; // axial q
-                let r = h.y; // axial r
-                let is_odd = (r & 1) != 0;
-                // axial (q,r) -> odd-r offset column
-                let col = q + ((r - (r & 1)) / 2);
-                let max_col = if is_odd { w - 2 } else { w - 1 };
-                col >= 0 && col <= max_col
-            })
-            .map(|pos| Tile {
-                position: pos,
+    // Deep water tiles in `shape`, listed row by row
+    pub fn with_shape(shape: MapShape, orientation: HexOrientation) -> Self {
+        let hex_size = 1.0;
+        let layout = HexLayout::new(orientation).with_hex_size(hex_size);
+        let offset = OffsetConvention::for_orientation(orientation);
+        let rectangle = |width: u32, height: u32, trimmed: bool| {
+            let (w, h) = (width as i32, height as i32);
+            (0..h)
+                .flat_map(move |row| (0..w).map(move |col| (col, row)))
+                // The shifted lines lose their last tile
+                .filter(move |&(col, row)| match offset {
+                    _ if !trimmed => true,
+                    OffsetConvention::OddR => row & 1 == 0 || col < w - 1,
+                    OffsetConvention::OddQ => col & 1 == 0 || row < h - 1,
+                })
+                .map(|(col, row)| offset.to_hex(col, row))
+                .collect::<Vec<_>>()
+        };
+        let mut positions = match shape {
+            MapShape::TrimmedRectangle { width, height } => rectangle(width, height, true),
+            MapShape::Rectangle { width, height } => rectangle(width, height, false),
+            MapShape::Hexagon { radius } => {
+                let center = offset.to_hex(radius as i32, radius as i32);
+                center.range(radius).collect()
+            }
+            MapShape::Parallelogram { width, height } => (0..height as i32)
+                .flat_map(|r| (0..width as i32).map(move |q| Hex::new(q, r)))
+                .collect(),
+        };
+        positions.sort_by_key(|&hex| {
+            let (col, row) = offset.to_offset(hex);
+            (row, col)
+        });
+        let tiles = positions
+            .into_iter()
+            .map(|position| Tile {
+                position,
                 terrain: Terrain::DeepWater,
+                building: None,
             })
-            .collect::<Vec<Tile>>();
-    Map { hex_size, layout, tiles }
+            .collect();
+        let map = Map::from_tiles(hex_size, layout, tiles);
+        match shape {
+            // Kept even where trimming empties the last column
+            MapShape::TrimmedRectangle { width, height } | MapShape::Rectangle { width, height } => {
+                map.with_size(width, height)
+            }
+            _ => map,
+        }
+    }
+
+    // The width and height are those of the offset rectangle around the tiles, see with_size
+    pub(crate) fn from_tiles(hex_size: f32, layout: HexLayout, tiles: Vec<Tile>) -> Self {
+        let index = tiles
+            .iter()
+            .enumerate()
+            .map(|(i, tile)| (tile.position, i))
+            .collect();
+        let offset = OffsetConvention::for_orientation(layout.orientation);
+        let mut map = Map {
+            hex_size,
+            layout,
+            tiles,
+            index,
+            width: 0,
+            height: 0,
+            offset,
+            world_bounds: None,
+            doubled_bounds: None,
+        };
+        map.world_bounds =
+            WorldBounds::around(map.tiles.iter().map(|tile| map.tile_to_world_pos(tile)));
+        map.doubled_bounds = map
+            .tiles
+            .iter()
+            .map(|tile| map.doubled(tile.position))
+            .fold(None, |bounds, (along, line)| match bounds {
+                None => Some(((along, line), (along, line))),
+                Some(((min_along, min_line), (max_along, max_line))) => Some((
+                    (min_along.min(along), min_line.min(line)),
+                    (max_along.max(along), max_line.max(line)),
+                )),
+            });
+        if let Some(((min_col, min_row), (max_col, max_row))) = map.offset_bounds() {
+            map.width = (max_col - min_col + 1) as u32;
+            map.height = (max_row - min_row + 1) as u32;
+        }
+        map
+    }
+
+    // Overrides the dimensions from_tiles derives, e.g. for odd rows trimmed to nothing
+    pub(crate) fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    // Overrides the convention from_tiles picks for the orientation
+    pub(crate) fn with_offset_convention(mut self, offset: OffsetConvention) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    // Number of columns, i.e. tiles in the long rows
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn orientation(&self) -> HexOrientation {
+        self.layout.orientation
+    }
+
+    pub fn offset_convention(&self) -> OffsetConvention {
+        self.offset
+    }
+
+    // Offset coordinates (col, row) of `hex` under the map's convention, see tile_at_offset
+    pub fn hex_to_offset(&self, hex: Hex) -> (i32, i32) {
+        self.offset.to_offset(hex)
+    }
+
+    pub fn offset_to_hex(&self, col: i32, row: i32) -> Hex {
+        self.offset.to_hex(col, row)
+    }
+
+    // Smallest and largest offset coordinates of the tiles, None for an empty map
+    pub(crate) fn offset_bounds(&self) -> Option<((i32, i32), (i32, i32))> {
+        let (first, rest) = self.tiles.split_first()?;
+        let first = self.hex_to_offset(first.position);
+        Some(rest.iter().fold((first, first), |(min, max), tile| {
+            let (col, row) = self.hex_to_offset(tile.position);
+            ((min.0.min(col), min.1.min(row)), (max.0.max(col), max.1.max(row)))
+        }))
+    }
+
+    // Smallest box around the tile centres in world space, None for an empty map
+    pub fn world_bounds(&self) -> Option<WorldBounds> {
+        self.world_bounds
+    }
+
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    pub fn tiles_mut(&mut self) -> &mut [Tile] {
+        &mut self.tiles
+    }
+
+    pub fn contains(&self, hex: Hex) -> bool {
+        self.index.contains_key(&hex)
+    }
+
+    // Position of the tile at `hex` in `tiles()`
+    pub fn tile_index(&self, hex: Hex) -> Option<usize> {
+        self.index.get(&hex).copied()
+    }
+
+    pub fn tile_at(&self, hex: Hex) -> Option<&Tile> {
+        self.tile_index(hex).map(|i| &self.tiles[i])
+    }
+
+    pub fn tile_at_mut(&mut self, hex: Hex) -> Option<&mut Tile> {
+        self.tile_index(hex).map(|i| &mut self.tiles[i])
+    }
+
+    // Lookup by offset coordinates, i.e. the column/row a tile has in the rectangle built by `new`
+    pub fn tile_at_offset(&self, col: i32, row: i32) -> Option<&Tile> {
+        self.tile_at(self.offset_to_hex(col, row))
+    }
+
+    // Adjacent tiles that are part of the map
+    pub fn neighbors(&self, hex: Hex) -> impl Iterator<Item = &Tile> + '_ {
+        hex.all_neighbors()
+            .into_iter()
+            .filter_map(|neighbor| self.tile_at(neighbor))
+    }
+
+    // Tiles at exactly `radius` steps from `center`
+    pub fn ring(&self, center: Hex, radius: u32) -> impl Iterator<Item = &Tile> + '_ {
+        center.ring(radius).filter_map(|hex| self.tile_at(hex))
+    }
+
+    // Tiles within `radius` steps of `center`, including `center` itself
+    pub fn range(&self, center: Hex, radius: u32) -> impl Iterator<Item = &Tile> + '_ {
+        center.range(radius).filter_map(|hex| self.tile_at(hex))
+    }
+
+    // Tiles on the straight line from `from` to `to`, both included
+    pub fn line(&self, from: Hex, to: Hex) -> impl Iterator<Item = &Tile> + '_ {
+        from.line_to(to).filter_map(|hex| self.tile_at(hex))
+    }
+
+    // Tiles in the offset rectangle (see tile_at_offset) with `from` and `to` as opposite corners
+    pub fn rectangle(&self, from: Hex, to: Hex) -> impl Iterator<Item = &Tile> + '_ {
+        let ((col_a, row_a), (col_b, row_b)) = (self.hex_to_offset(from), self.hex_to_offset(to));
+        let cols = col_a.min(col_b)..=col_a.max(col_b);
+        (row_a.min(row_b)..=row_a.max(row_b))
+            .flat_map(move |row| cols.clone().map(move |col| (col, row)))
+            .filter_map(|(col, row)| self.tile_at_offset(col, row))
+    }
+
+    // The tile at `start` and all tiles of the same terrain connected to it, i.e. what a
+    // bucket fill replaces. Empty if `start` is not on the map.
+    pub fn region(&self, start: Hex) -> Vec<&Tile> {
+        let Some(first) = self.tile_at(start) else {
+            return Vec::new();
+        };
+        let mut seen = HashSet::from([start]);
+        let mut region = vec![first];
+        let mut next = 0;
+        while let Some(tile) = region.get(next) {
+            next += 1;
+            let neighbors = self
+                .neighbors(tile.position)
+                .filter(|neighbor| neighbor.terrain == first.terrain)
+                .filter(|neighbor| seen.insert(neighbor.position))
+                .collect::<Vec<_>>();
+            region.extend(neighbors);
+        }
+        region
+    }
+
+    // The tile `hex` maps to under `symmetry`, mirrored within the rectangle the tiles span.
+    // Rows (columns of flat-top hexes) alternate by half a tile, so when the mirrored row has
+    // the other parity (e.g. top-bottom with an even number of pointy-top rows) there is no
+    // exact image and a neighbour of it is used, which may be off the map. Mirroring the
+    // result again gives back `hex`.
+    pub fn mirror_hex(&self, hex: Hex, symmetry: Symmetry) -> Option<Hex> {
+        let ((along_min, line_min), (along_max, line_max)) = self.doubled_bounds?;
+        let flat = self.orientation() == HexOrientation::Flat;
+        // Left-right runs along pointy-top rows but across flat-top columns
+        let (mirror_along, mirror_across) = match (symmetry, flat) {
+            (Symmetry::Horizontal, false) | (Symmetry::Vertical, true) => (true, false),
+            (Symmetry::Vertical, false) | (Symmetry::Horizontal, true) => (false, true),
+            (Symmetry::Point, _) => (true, true),
+        };
+        let (mut along, mut line) = self.doubled(hex);
+        if mirror_along {
+            along = along_min + along_max - along;
+        }
+        if mirror_across {
+            line = line_min + line_max - line;
+        }
+        // Half a tile off; shifting by a fixed amount keeps mirroring its own inverse
+        if (along - line) & 1 != 0 {
+            along = if mirror_along { along - 1 } else { along ^ 1 };
+        }
+        let half = (along - line) / 2;
+        let mirrored = if flat { Hex::new(line, half) } else { Hex::new(half, line) };
+        self.contains(mirrored).then_some(mirrored)
+    }
+
+    // Doubled coordinates: the position along the row (column of flat-top hexes) in half
+    // tiles, which has the parity of the row, and the row itself
+    fn doubled(&self, hex: Hex) -> (i32, i32) {
+        match self.orientation() {
+            HexOrientation::Pointy => (2 * hex.x + hex.y, hex.y),
+            HexOrientation::Flat => (2 * hex.y + hex.x, hex.x),
+        }
     }
 
     pub fn tile_to_world_pos(&self, tile: &Tile) -> (f32, f32) {
-    let pos = self.layout.hex_to_world_pos(tile.position);
-    (pos.x as f32, pos.y as f32)
+        self.hex_to_world_pos(tile.position)
+    }
+
+    // Like tile_to_world_pos for any hex, on the map or not
+    pub fn hex_to_world_pos(&self, hex: Hex) -> (f32, f32) {
+        let pos = self.layout.hex_to_world_pos(hex);
+        (pos.x as f32, pos.y as f32)
+    }
+
+    // Inverse of tile_to_world_pos: the hex containing the given world position,
+    // which may lie outside the map
+    pub fn world_pos_to_hex(&self, x: f32, y: f32) -> Hex {
+        self.layout.world_pos_to_hex(hexx::Vec2::new(x, y))
     }
 
     pub fn hex_size(&self) -> f32 {
@@ -64,7 +327,99 @@ impl Map {
     }
 }
 
+// Outline of a map, see Map::with_shape. Sizes are in tiles.
+#[derive(PartialEq, Clone, Debug, Copy, Eq, Hash)]
+pub enum MapShape {
+    // A rectangle in offset coordinates whose shifted rows (columns for flat-top hexes) are
+    // a tile short, so all four edges are straight
+    TrimmedRectangle { width: u32, height: u32 },
+    // A rectangle in offset coordinates with the shifted rows or columns sticking out
+    Rectangle { width: u32, height: u32 },
+    // Every tile within `radius` steps of the centre
+    Hexagon { radius: u32 },
+    // `width` tiles along each of `height` rows or columns, each one shifted by half a tile
+    // against the one before
+    Parallelogram { width: u32, height: u32 },
+}
+
+// How the columns and rows of a map map to hexes
+#[derive(PartialEq, Clone, Debug, Copy, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum OffsetConvention {
+    // Rows of pointy-top hexes with the odd rows pushed right by half a tile
+    #[default]
+    OddR,
+    // Columns of flat-top hexes with the odd columns pushed down by half a tile
+    OddQ,
+}
+
+impl OffsetConvention {
+    pub fn for_orientation(orientation: HexOrientation) -> Self {
+        match orientation {
+            HexOrientation::Pointy => OffsetConvention::OddR,
+            HexOrientation::Flat => OffsetConvention::OddQ,
+        }
+    }
+
+    pub fn to_offset(self, hex: Hex) -> (i32, i32) {
+        match self {
+            OffsetConvention::OddR => (hex.x + (hex.y - (hex.y & 1)) / 2, hex.y),
+            OffsetConvention::OddQ => (hex.x, hex.y + (hex.x - (hex.x & 1)) / 2),
+        }
+    }
+
+    pub fn to_hex(self, col: i32, row: i32) -> Hex {
+        match self {
+            OffsetConvention::OddR => Hex::new(col - (row - (row & 1)) / 2, row),
+            OffsetConvention::OddQ => Hex::new(col, row - (col - (col & 1)) / 2),
+        }
+    }
+}
+
+// Axis-aligned box in world space, see Map::world_bounds
+#[derive(PartialEq, Clone, Debug, Copy)]
+pub struct WorldBounds {
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+}
+
+impl WorldBounds {
+    // None if there are no points
+    pub fn around(points: impl IntoIterator<Item = (f32, f32)>) -> Option<WorldBounds> {
+        points.into_iter().fold(None, |bounds, (x, y)| {
+            Some(match bounds {
+                None => WorldBounds {
+                    min: (x, y),
+                    max: (x, y),
+                },
+                Some(WorldBounds { min, max }) => WorldBounds {
+                    min: (min.0.min(x), min.1.min(y)),
+                    max: (max.0.max(x), max.1.max(y)),
+                },
+            })
+        })
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        ((self.min.0 + self.max.0) * 0.5, (self.min.1 + self.max.1) * 0.5)
+    }
+
+    pub fn size(&self) -> (f32, f32) {
+        (self.max.0 - self.min.0, self.max.1 - self.min.1)
+    }
+}
+
+// Ways a map can be symmetric, see Map::mirror_hex
 #[derive(PartialEq, Clone, Debug, Copy, Eq, Hash)]
+pub enum Symmetry {
+    // The left half mirrors the right half
+    Horizontal,
+    // The top half mirrors the bottom half
+    Vertical,
+    // Turned by 180° around the centre of the map
+    Point,
+}
+
+#[derive(PartialEq, Clone, Debug, Copy, Eq, Hash, Serialize, Deserialize)]
 pub enum Terrain {
     Plains,
     Hills,
@@ -73,6 +428,34 @@ pub enum Terrain {
     ShallowWater,
 }
 
+// Height class of a terrain, ordered from lowest to highest
+#[derive(PartialEq, Clone, Debug, Copy, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Elevation {
+    Water,
+    Plains,
+    Hills,
+    Mountains,
+}
+
+impl Terrain {
+    pub const ALL: [Terrain; 5] = [
+        Terrain::Plains,
+        Terrain::Hills,
+        Terrain::Mountains,
+        Terrain::DeepWater,
+        Terrain::ShallowWater,
+    ];
+
+    pub fn elevation(self) -> Elevation {
+        match self {
+            Terrain::DeepWater | Terrain::ShallowWater => Elevation::Water,
+            Terrain::Plains => Elevation::Plains,
+            Terrain::Hills => Elevation::Hills,
+            Terrain::Mountains => Elevation::Mountains,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,10 +466,12 @@ mod tests {
         let sut = Tile {
             position: Hex::new(0, 0),
             terrain: Terrain::Plains,
+            building: None,
         };
         assert_eq!(sut.position.x, 0);
         assert_eq!(sut.position.y, 0);
         assert_eq!(sut.terrain, Terrain::Plains);
+        assert_eq!(sut.building, None);
     }
 
     #[rstest]
@@ -103,4 +488,363 @@ mod tests {
             assert_eq!(tile.terrain, Terrain::DeepWater);
         });
     }
+
+    #[rstest]
+    #[case(10, 10)]
+    #[case(5, 4)]
+    #[case(1, 4)] // odd rows are trimmed to nothing
+    #[case(0, 3)]
+    fn test_map_keeps_its_size(#[case] width: u32, #[case] height: u32) {
+        let sut = Map::new(width, height);
+        assert_eq!((sut.width(), sut.height()), (width, height));
+        assert_eq!(sut.offset_convention(), OffsetConvention::OddR);
+    }
+
+    #[rstest]
+    #[case(MapShape::TrimmedRectangle { width: 5, height: 5 }, HexOrientation::Pointy, 23, (5, 5))]
+    #[case(MapShape::TrimmedRectangle { width: 5, height: 5 }, HexOrientation::Flat, 23, (5, 5))]
+    #[case(MapShape::TrimmedRectangle { width: 4, height: 1 }, HexOrientation::Flat, 2, (4, 1))]
+    #[case(MapShape::Rectangle { width: 5, height: 4 }, HexOrientation::Pointy, 20, (5, 4))]
+    #[case(MapShape::Rectangle { width: 5, height: 4 }, HexOrientation::Flat, 20, (5, 4))]
+    #[case(MapShape::Hexagon { radius: 2 }, HexOrientation::Pointy, 19, (5, 5))]
+    #[case(MapShape::Hexagon { radius: 3 }, HexOrientation::Flat, 37, (7, 7))]
+    #[case(MapShape::Hexagon { radius: 0 }, HexOrientation::Flat, 1, (1, 1))]
+    #[case(MapShape::Parallelogram { width: 3, height: 2 }, HexOrientation::Pointy, 6, (3, 2))]
+    #[case(MapShape::Parallelogram { width: 3, height: 2 }, HexOrientation::Flat, 6, (3, 3))]
+    fn test_map_shapes(
+        #[case] shape: MapShape,
+        #[case] orientation: HexOrientation,
+        #[case] expected_tile_count: usize,
+        #[case] expected_size: (u32, u32),
+    ) {
+        let sut = Map::with_shape(shape, orientation);
+        assert_eq!(sut.tiles().len(), expected_tile_count);
+        assert_eq!((sut.width(), sut.height()), expected_size);
+        assert_eq!(sut.orientation(), orientation);
+        // Row by row, starting at the top left
+        let offsets = sut
+            .tiles()
+            .iter()
+            .map(|tile| sut.hex_to_offset(tile.position()))
+            .collect::<Vec<_>>();
+        assert!(offsets.windows(2).all(|pair| (pair[0].1, pair[0].0) < (pair[1].1, pair[1].0)));
+        let ((min_col, min_row), _) = sut.offset_bounds().unwrap();
+        assert_eq!((min_col, min_row), (0, 0));
+    }
+
+    #[test]
+    fn test_hexagon_is_centred() {
+        let sut = Map::with_shape(MapShape::Hexagon { radius: 3 }, HexOrientation::Pointy);
+        let center = sut.offset_to_hex(3, 3);
+        assert!(sut.tiles().iter().all(|tile| tile.position().unsigned_distance_to(center) <= 3));
+    }
+
+    #[test]
+    fn test_from_tiles_derives_the_size() {
+        let map = Map::new(6, 4);
+        let sut = Map::from_tiles(map.hex_size, map.layout.clone(), map.tiles.clone());
+        assert_eq!((sut.width(), sut.height()), (6, 4));
+    }
+
+    #[rstest]
+    #[case(0, 0, Hex::new(0, 0))]
+    #[case(3, 1, Hex::new(3, 1))]
+    #[case(2, 4, Hex::new(0, 4))]
+    #[case(0, 3, Hex::new(-1, 3))]
+    #[case(-1, -1, Hex::new(0, -1))]
+    fn test_offset_conversion(#[case] col: i32, #[case] row: i32, #[case] expected: Hex) {
+        let sut = Map::new(5, 5);
+        assert_eq!(sut.offset_to_hex(col, row), expected);
+        assert_eq!(sut.hex_to_offset(expected), (col, row));
+    }
+
+    #[rstest]
+    #[case(0, 0, Hex::new(0, 0))]
+    #[case(1, 0, Hex::new(1, 0))]
+    #[case(1, 3, Hex::new(1, 3))]
+    #[case(2, 2, Hex::new(2, 1))]
+    #[case(4, 4, Hex::new(4, 2))]
+    #[case(-1, -1, Hex::new(-1, 0))]
+    fn test_flat_offset_conversion(#[case] col: i32, #[case] row: i32, #[case] expected: Hex) {
+        let sut = Map::with_shape(MapShape::TrimmedRectangle { width: 5, height: 5 }, HexOrientation::Flat);
+        assert_eq!(sut.offset_convention(), OffsetConvention::OddQ);
+        assert_eq!(sut.offset_to_hex(col, row), expected);
+        assert_eq!(sut.hex_to_offset(expected), (col, row));
+        // Odd columns are a tile short
+        assert!(sut.tile_at_offset(1, 3).is_some());
+        assert!(sut.tile_at_offset(1, 4).is_none());
+    }
+
+    #[test]
+    fn test_world_bounds() {
+        let sut = Map::new(5, 5);
+        let positions = sut
+            .tiles()
+            .iter()
+            .map(|tile| sut.tile_to_world_pos(tile))
+            .collect::<Vec<_>>();
+        let bounds = sut.world_bounds().unwrap();
+        for (x, y) in &positions {
+            assert!((bounds.min.0..=bounds.max.0).contains(x));
+            assert!((bounds.min.1..=bounds.max.1).contains(y));
+        }
+        let on_edge = |pick: fn(&(f32, f32)) -> f32, value: f32| {
+            positions.iter().any(|pos| pick(pos) == value)
+        };
+        assert!(on_edge(|pos| pos.0, bounds.min.0) && on_edge(|pos| pos.0, bounds.max.0));
+        assert!(on_edge(|pos| pos.1, bounds.min.1) && on_edge(|pos| pos.1, bounds.max.1));
+        assert_eq!(Map::new(0, 0).world_bounds(), None);
+    }
+
+    #[rstest]
+    #[case(0, 0, Some(Hex::new(0, 0)))]
+    #[case(4, 0, Some(Hex::new(4, 0)))]
+    #[case(0, 1, Some(Hex::new(0, 1)))]
+    #[case(3, 1, Some(Hex::new(3, 1)))]
+    #[case(4, 1, None)]
+    #[case(2, 4, Some(Hex::new(0, 4)))]
+    #[case(5, 0, None)]
+    #[case(-1, 0, None)]
+    fn test_tile_at_offset(#[case] col: i32, #[case] row: i32, #[case] expected: Option<Hex>) {
+        let sut = Map::new(5, 5);
+        assert_eq!(sut.tile_at_offset(col, row).map(Tile::position), expected);
+    }
+
+    #[test]
+    fn test_tile_at() {
+        let mut sut = Map::new(5, 5);
+        sut.tile_at_mut(Hex::new(1, 2)).unwrap().terrain = Terrain::Hills;
+
+        assert!(sut.contains(Hex::new(1, 2)));
+        assert!(!sut.contains(Hex::new(-1, 0)));
+        assert_eq!(sut.tile_at(Hex::new(1, 2)).unwrap().terrain, Terrain::Hills);
+        assert!(sut.tile_at(Hex::new(10, 10)).is_none());
+        sut.tiles().iter().enumerate().for_each(|(i, tile)| {
+            assert_eq!(sut.tile_index(tile.position()), Some(i));
+        });
+    }
+
+    #[rstest]
+    #[case(0, 0, 2)] // top left corner
+    #[case(4, 0, 2)] // top right corner
+    #[case(0, 1, 5)] // left edge, odd row
+    #[case(3, 1, 5)] // last tile of a trimmed odd row
+    #[case(2, 2, 6)] // interior
+    #[case(4, 4, 2)] // bottom right corner
+    fn test_neighbors(#[case] col: i32, #[case] row: i32, #[case] expected_count: usize) {
+        let sut = Map::new(5, 5);
+        let hex = sut.tile_at_offset(col, row).unwrap().position();
+        let neighbors = sut.neighbors(hex).collect::<Vec<_>>();
+        assert_eq!(neighbors.len(), expected_count);
+        neighbors.iter().for_each(|tile| {
+            assert_eq!(tile.position().unsigned_distance_to(hex), 1);
+        });
+    }
+
+    #[rstest]
+    #[case(2, 2, 0, 1, 1)]
+    #[case(2, 2, 1, 6, 7)]
+    #[case(2, 2, 2, 12, 19)]
+    #[case(0, 0, 1, 2, 3)]
+    #[case(0, 0, 10, 0, 23)]
+    fn test_ring_and_range(
+        #[case] col: i32,
+        #[case] row: i32,
+        #[case] radius: u32,
+        #[case] expected_ring: usize,
+        #[case] expected_range: usize,
+    ) {
+        let sut = Map::new(5, 5);
+        let hex = sut.tile_at_offset(col, row).unwrap().position();
+        assert_eq!(sut.ring(hex, radius).count(), expected_ring);
+        assert_eq!(sut.range(hex, radius).count(), expected_range);
+    }
+
+    #[rstest]
+    #[case(Hex::new(0, 0), Hex::new(4, 0), 5)]
+    #[case(Hex::new(2, 0), Hex::new(2, 4), 5)]
+    #[case(Hex::new(1, 1), Hex::new(1, 1), 1)]
+    #[case(Hex::new(0, 0), Hex::new(6, 0), 5)] // clipped to the map
+    fn test_line(#[case] from: Hex, #[case] to: Hex, #[case] expected_count: usize) {
+        let sut = Map::new(5, 5);
+        let line = sut.line(from, to).map(Tile::position).collect::<Vec<_>>();
+        assert_eq!(line.len(), expected_count);
+        assert_eq!(line.first(), Some(&from));
+        line.windows(2).for_each(|pair| {
+            assert_eq!(pair[0].unsigned_distance_to(pair[1]), 1);
+        });
+    }
+
+    #[rstest]
+    #[case((0, 0), (4, 4), 23)] // whole map
+    #[case((4, 4), (0, 0), 23)]
+    #[case((-3, -3), (9, 9), 23)] // corners outside the map
+    #[case((1, 1), (2, 3), 6)]
+    #[case((0, 1), (4, 1), 4)] // odd rows are a tile short
+    #[case((3, 3), (3, 3), 1)]
+    fn test_rectangle(
+        #[case] from: (i32, i32),
+        #[case] to: (i32, i32),
+        #[case] expected_count: usize,
+    ) {
+        let sut = Map::new(5, 5);
+        let at_offset = |(col, row): (i32, i32)| sut.offset_to_hex(col, row);
+        let rectangle = sut.rectangle(at_offset(from), at_offset(to)).collect::<Vec<_>>();
+        assert_eq!(rectangle.len(), expected_count);
+        rectangle.iter().for_each(|tile| {
+            assert_eq!(at_offset(sut.hex_to_offset(tile.position())), tile.position());
+        });
+    }
+
+    #[test]
+    fn test_region() {
+        let mut sut = Map::new(5, 5);
+        // A wall of hills along column 2 splits the water in two
+        for row in 0..5 {
+            let hex = sut.tile_at_offset(2, row).unwrap().position();
+            sut.tile_at_mut(hex).unwrap().terrain = Terrain::Hills;
+        }
+        let left = sut.tile_at_offset(0, 0).unwrap().position();
+        let wall = sut.tile_at_offset(2, 0).unwrap().position();
+
+        let region = sut.region(left);
+        assert_eq!(region.first().map(|tile| tile.position()), Some(left));
+        assert!(region.iter().all(|tile| tile.terrain == Terrain::DeepWater));
+        assert!(region.iter().all(|tile| sut.hex_to_offset(tile.position()).0 < 2));
+        assert_eq!(region.len(), 10);
+        assert_eq!(sut.region(wall).len(), 5);
+        assert!(sut.region(Hex::new(-5, 0)).is_empty());
+    }
+
+    #[rstest]
+    #[case(Symmetry::Horizontal, (0, 0), Some((4, 0)))]
+    #[case(Symmetry::Horizontal, (0, 1), Some((3, 1)))] // odd rows are a tile short
+    #[case(Symmetry::Horizontal, (2, 2), Some((2, 2)))] // on the axis
+    #[case(Symmetry::Vertical, (1, 0), Some((1, 4)))]
+    #[case(Symmetry::Vertical, (3, 1), Some((3, 3)))]
+    #[case(Symmetry::Point, (0, 0), Some((4, 4)))]
+    #[case(Symmetry::Point, (0, 1), Some((3, 3)))]
+    #[case(Symmetry::Point, (2, 2), Some((2, 2)))] // the centre
+    fn test_mirror_hex(
+        #[case] symmetry: Symmetry,
+        #[case] from: (i32, i32),
+        #[case] expected: Option<(i32, i32)>,
+    ) {
+        let sut = Map::new(5, 5);
+        let hex = sut.tile_at_offset(from.0, from.1).unwrap().position();
+        let mirrored = sut.mirror_hex(hex, symmetry);
+        assert_eq!(mirrored.map(|hex| sut.hex_to_offset(hex)), expected);
+    }
+
+    #[rstest]
+    #[case(5, 5, Symmetry::Horizontal, 0)]
+    #[case(5, 5, Symmetry::Vertical, 0)]
+    #[case(5, 5, Symmetry::Point, 0)]
+    #[case(6, 4, Symmetry::Horizontal, 0)]
+    // With an even number of rows the top row is a full one and the bottom row a short
+    // one, so a tile in every full row has no image
+    #[case(6, 4, Symmetry::Vertical, 2)]
+    #[case(6, 4, Symmetry::Point, 2)]
+    fn test_mirroring_twice_gives_the_tile_back(
+        #[case] width: u32,
+        #[case] height: u32,
+        #[case] symmetry: Symmetry,
+        #[case] expected_unmirrored: usize,
+    ) {
+        let sut = Map::new(width, height);
+        let mut unmirrored = 0;
+        for tile in sut.tiles() {
+            match sut.mirror_hex(tile.position(), symmetry) {
+                Some(mirrored) => {
+                    assert_eq!(sut.mirror_hex(mirrored, symmetry), Some(tile.position()));
+                    if symmetry != Symmetry::Horizontal {
+                        let rows = (height - 1) as i32;
+                        let (_, row) = sut.hex_to_offset(tile.position());
+                        assert_eq!(sut.hex_to_offset(mirrored).1, rows - row);
+                    }
+                }
+                None => unmirrored += 1,
+            }
+        }
+        assert_eq!(unmirrored, expected_unmirrored);
+    }
+
+    #[rstest]
+    #[case(Symmetry::Horizontal, (0, 1), Some((4, 1)))]
+    #[case(Symmetry::Vertical, (0, 0), Some((0, 4)))]
+    #[case(Symmetry::Vertical, (1, 0), Some((1, 3)))] // odd columns are a tile short
+    #[case(Symmetry::Point, (1, 0), Some((3, 3)))]
+    #[case(Symmetry::Point, (2, 2), Some((2, 2)))] // the centre
+    fn test_mirror_hex_on_flat_top_maps(
+        #[case] symmetry: Symmetry,
+        #[case] from: (i32, i32),
+        #[case] expected: Option<(i32, i32)>,
+    ) {
+        let sut = Map::with_shape(MapShape::TrimmedRectangle { width: 5, height: 5 }, HexOrientation::Flat);
+        let mirrored = sut.mirror_hex(sut.offset_to_hex(from.0, from.1), symmetry);
+        assert_eq!(mirrored.map(|hex| sut.hex_to_offset(hex)), expected);
+    }
+
+    #[rstest]
+    #[case(MapShape::TrimmedRectangle { width: 4, height: 6 }, HexOrientation::Flat, [0, 2, 2])]
+    // The odd rows stick out on the right
+    #[case(MapShape::Rectangle { width: 5, height: 5 }, HexOrientation::Pointy, [0, 2, 2])]
+    #[case(MapShape::Hexagon { radius: 3 }, HexOrientation::Pointy, [0, 0, 0])]
+    #[case(MapShape::Hexagon { radius: 3 }, HexOrientation::Flat, [0, 0, 0])]
+    fn test_mirroring_other_shapes_twice_gives_the_tile_back(
+        #[case] shape: MapShape,
+        #[case] orientation: HexOrientation,
+        #[case] expected_unmirrored: [usize; 3],
+    ) {
+        let sut = Map::with_shape(shape, orientation);
+        let symmetries = [Symmetry::Vertical, Symmetry::Horizontal, Symmetry::Point];
+        for (symmetry, expected) in symmetries.into_iter().zip(expected_unmirrored) {
+            let mut unmirrored = 0;
+            for tile in sut.tiles() {
+                match sut.mirror_hex(tile.position(), symmetry) {
+                    Some(mirrored) => {
+                        assert_eq!(sut.mirror_hex(mirrored, symmetry), Some(tile.position()))
+                    }
+                    None => unmirrored += 1,
+                }
+            }
+            assert_eq!(unmirrored, expected, "{:?}", symmetry);
+        }
+    }
+
+    #[test]
+    fn test_mirroring_a_large_map() {
+        // Mirroring is done per painted tile, so it must not scan the map each time
+        let sut = Map::new(200, 200);
+        let unmirrored = sut
+            .tiles()
+            .iter()
+            .filter(|tile| match sut.mirror_hex(tile.position(), Symmetry::Point) {
+                Some(mirrored) => {
+                    assert_eq!(sut.mirror_hex(mirrored, Symmetry::Point), Some(tile.position()));
+                    false
+                }
+                None => true,
+            })
+            .count();
+        // A tile in every full row, as in the 6x4 case above
+        assert_eq!(unmirrored, 100);
+    }
+
+    #[test]
+    fn test_mirror_hex_on_an_empty_map() {
+        assert_eq!(Map::new(0, 0).mirror_hex(Hex::ZERO, Symmetry::Point), None);
+    }
+
+    #[rstest]
+    #[case(0.0, 0.0)]
+    #[case(0.4, -0.3)]
+    #[case(-0.5, 0.5)]
+    fn test_world_pos_to_hex(#[case] dx: f32, #[case] dy: f32) {
+        let sut = Map::new(10, 10);
+        sut.tiles.iter().for_each(|tile| {
+            let (x, y) = sut.tile_to_world_pos(tile);
+            assert_eq!(sut.world_pos_to_hex(x + dx, y + dy), tile.position());
+        });
+    }
 }
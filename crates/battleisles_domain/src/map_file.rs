@@ -0,0 +1,318 @@
+use crate::building::Building;
+use crate::map::{Hex, Map, OffsetConvention, Terrain, Tile};
+use hexx::{HexLayout, HexOrientation};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// Current version of the on-disk map format. Bump this whenever the layout of
+// `MapFile` changes in a way older readers cannot understand.
+//
+// History: 1 terrain only, 2 adds buildings, 3 adds the map size and offset convention.
+pub const MAP_FILE_VERSION: u32 = 3;
+
+// Serialized form of a `Map`. Written as pretty RON so map files diff nicely.
+#[derive(Serialize, Deserialize)]
+struct MapFile {
+    version: u32,
+    hex_size: f32,
+    orientation: HexOrientation,
+    // Absent before version 3, where they are derived from the tiles
+    #[serde(default)]
+    width: u32,
+    #[serde(default)]
+    height: u32,
+    #[serde(default)]
+    offset: OffsetConvention,
+    tiles: Vec<TileRecord>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TileRecord {
+    q: i32,
+    r: i32,
+    terrain: Terrain,
+    // Absent in version 1 files and omitted for empty tiles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    building: Option<Building>,
+}
+
+// Only the version is read first so that files from a newer format are
+// reported as such instead of as a parse error.
+#[derive(Deserialize)]
+struct MapFileHeader {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum MapFileError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    UnsupportedVersion { found: u32, supported: u32 },
+    InvalidHexSize(f32),
+    DuplicateTile { q: i32, r: i32 },
+}
+
+impl fmt::Display for MapFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapFileError::Io(e) => write!(f, "I/O error: {e}"),
+            MapFileError::Parse(e) => write!(f, "malformed map file: {e}"),
+            MapFileError::Serialize(e) => write!(f, "failed to serialize map: {e}"),
+            MapFileError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "map file version {found} is not supported (latest supported is {supported})"
+            ),
+            MapFileError::InvalidHexSize(size) => write!(f, "invalid hex size: {size}"),
+            MapFileError::DuplicateTile { q, r } => write!(f, "duplicate tile at ({q}, {r})"),
+        }
+    }
+}
+
+impl std::error::Error for MapFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MapFileError::Io(e) => Some(e),
+            MapFileError::Parse(e) => Some(e),
+            MapFileError::Serialize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for MapFileError {
+    fn from(e: std::io::Error) -> Self {
+        MapFileError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for MapFileError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        MapFileError::Parse(e)
+    }
+}
+
+impl From<ron::Error> for MapFileError {
+    fn from(e: ron::Error) -> Self {
+        MapFileError::Serialize(e)
+    }
+}
+
+impl Map {
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), MapFileError> {
+        fs::write(path, self.to_ron_string()?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Map, MapFileError> {
+        Map::from_ron_str(&fs::read_to_string(path)?)
+    }
+
+    pub fn to_ron_string(&self) -> Result<String, MapFileError> {
+        let file = MapFile {
+            version: MAP_FILE_VERSION,
+            hex_size: self.hex_size,
+            orientation: self.layout.orientation,
+            width: self.width(),
+            height: self.height(),
+            offset: self.offset_convention(),
+            tiles: self
+                .tiles
+                .iter()
+                .map(|tile| TileRecord {
+                    q: tile.position.x,
+                    r: tile.position.y,
+                    terrain: tile.terrain,
+                    building: tile.building,
+                })
+                .collect(),
+        };
+        let config = ron::ser::PrettyConfig::new().depth_limit(2);
+        Ok(ron::ser::to_string_pretty(&file, config)?)
+    }
+
+    pub fn from_ron_str(s: &str) -> Result<Map, MapFileError> {
+        let header: MapFileHeader = ron::from_str(s)?;
+        if header.version == 0 || header.version > MAP_FILE_VERSION {
+            return Err(MapFileError::UnsupportedVersion {
+                found: header.version,
+                supported: MAP_FILE_VERSION,
+            });
+        }
+
+        let file: MapFile = ron::from_str(s)?;
+        if !file.hex_size.is_finite() || file.hex_size <= 0.0 {
+            return Err(MapFileError::InvalidHexSize(file.hex_size));
+        }
+
+        let mut seen = HashSet::with_capacity(file.tiles.len());
+        let mut tiles = Vec::with_capacity(file.tiles.len());
+        for TileRecord {
+            q,
+            r,
+            terrain,
+            building,
+        } in file.tiles
+        {
+            if !seen.insert((q, r)) {
+                return Err(MapFileError::DuplicateTile { q, r });
+            }
+            tiles.push(Tile {
+                position: Hex::new(q, r),
+                terrain,
+                building,
+            });
+        }
+
+        let map = Map::from_tiles(
+            file.hex_size,
+            HexLayout::new(file.orientation).with_hex_size(file.hex_size),
+            tiles,
+        );
+        Ok(match header.version {
+            1 | 2 => map,
+            _ => map
+                .with_offset_convention(file.offset)
+                .with_size(file.width, file.height),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::building::BuildingKind;
+    use crate::map::MapShape;
+    use crate::player::PlayerId;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(10, 10)]
+    #[case(5, 5)]
+    #[case(0, 0)]
+    fn test_map_round_trip(#[case] width: u32, #[case] height: u32) {
+        let mut map = Map::new(width, height);
+        for (i, tile) in map.tiles.iter_mut().enumerate() {
+            tile.terrain = match i % 3 {
+                0 => Terrain::Plains,
+                1 => Terrain::Mountains,
+                _ => Terrain::ShallowWater,
+            };
+        }
+
+        let text = map.to_ron_string().unwrap();
+        let sut = Map::from_ron_str(&text).unwrap();
+
+        assert_eq!(sut.hex_size, map.hex_size);
+        assert_eq!(sut.layout.orientation, map.layout.orientation);
+        assert_eq!(sut.tiles, map.tiles);
+        assert_eq!((sut.width(), sut.height()), (width, height));
+        assert_eq!(sut.offset_convention(), map.offset_convention());
+    }
+
+    #[rstest]
+    #[case(MapShape::TrimmedRectangle { width: 5, height: 4 }, HexOrientation::Flat)]
+    #[case(MapShape::Hexagon { radius: 3 }, HexOrientation::Pointy)]
+    #[case(MapShape::Parallelogram { width: 4, height: 3 }, HexOrientation::Flat)]
+    fn test_shaped_map_round_trip(#[case] shape: MapShape, #[case] orientation: HexOrientation) {
+        let map = Map::with_shape(shape, orientation);
+        let sut = Map::from_ron_str(&map.to_ron_string().unwrap()).unwrap();
+        assert_eq!(sut.orientation(), orientation);
+        assert_eq!(sut.offset_convention(), map.offset_convention());
+        assert_eq!(sut.tiles, map.tiles);
+        assert_eq!((sut.width(), sut.height()), (map.width(), map.height()));
+    }
+
+    #[test]
+    fn test_size_survives_trimmed_rows() {
+        // The odd rows are empty, so the tiles alone would make this a 1x3 map
+        let map = Map::new(1, 4);
+        let sut = Map::from_ron_str(&map.to_ron_string().unwrap()).unwrap();
+        assert_eq!((sut.width(), sut.height()), (1, 4));
+    }
+
+    #[test]
+    fn test_map_save_and_load() {
+        let path = std::env::temp_dir().join(format!("battleisles_map_{}.ron", std::process::id()));
+        let mut map = Map::new(4, 3);
+        map.tiles[0].terrain = Terrain::Hills;
+        map.tiles[1].building = Some(Building::new(BuildingKind::Factory, Some(PlayerId(2))));
+        map.tiles[2].building = Some(Building::new(BuildingKind::Depot, None));
+
+        map.save(&path).unwrap();
+        let sut = Map::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(sut.tiles, map.tiles);
+    }
+
+    #[test]
+    fn test_version_1_file_is_loaded_without_buildings() {
+        let text = "(version: 1, hex_size: 1.0, orientation: Pointy, tiles: [\
+            (q: 0, r: 0, terrain: Plains), (q: 1, r: 0, terrain: Hills)])";
+        let sut = Map::from_ron_str(text).unwrap();
+        assert_eq!(sut.tiles.len(), 2);
+        assert!(sut.tiles.iter().all(|tile| tile.building.is_none()));
+        assert_eq!((sut.width(), sut.height()), (2, 1));
+    }
+
+    #[test]
+    fn test_building_is_written_only_where_present() {
+        let mut map = Map::new(2, 1);
+        map.tiles[0].building = Some(Building::new(BuildingKind::Headquarters, Some(PlayerId(1))));
+
+        let text = map.to_ron_string().unwrap();
+
+        assert_eq!(text.matches("building").count(), 1);
+        assert!(text.contains("owner: Some(1)"));
+    }
+
+    #[test]
+    fn test_future_version_is_rejected() {
+        let text = "(version: 99, hex_size: 1.0, orientation: Pointy, tiles: [], extra: true)";
+        let sut = Map::from_ron_str(text);
+        assert!(matches!(
+            sut,
+            Err(MapFileError::UnsupportedVersion {
+                found: 99,
+                supported: MAP_FILE_VERSION
+            })
+        ));
+    }
+
+    #[rstest]
+    #[case("not a map")]
+    #[case("(version: 1, hex_size: 1.0, orientation: Pointy)")]
+    #[case(
+        "(version: 1, hex_size: 1.0, orientation: Pointy, tiles: [(q: 0, r: 0, terrain: Lava)])"
+    )]
+    fn test_malformed_file_is_rejected(#[case] text: &str) {
+        assert!(matches!(
+            Map::from_ron_str(text),
+            Err(MapFileError::Parse(_))
+        ));
+    }
+
+    #[rstest]
+    #[case("(version: 1, hex_size: 0.0, orientation: Pointy, tiles: [])")]
+    #[case("(version: 1, hex_size: -2.0, orientation: Flat, tiles: [])")]
+    fn test_invalid_hex_size_is_rejected(#[case] text: &str) {
+        assert!(matches!(
+            Map::from_ron_str(text),
+            Err(MapFileError::InvalidHexSize(_))
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_tile_is_rejected() {
+        let text = "(version: 1, hex_size: 1.0, orientation: Pointy, tiles: [\
+            (q: 0, r: 0, terrain: Plains), (q: 0, r: 0, terrain: Hills)])";
+        assert!(matches!(
+            Map::from_ron_str(text),
+            Err(MapFileError::DuplicateTile { q: 0, r: 0 })
+        ));
+    }
+}
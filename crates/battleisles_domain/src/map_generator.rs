@@ -0,0 +1,308 @@
+use crate::map::{Elevation, Hex, Map, Terrain, WorldBounds};
+use crate::rng::SeededRng;
+use std::collections::HashSet;
+
+// Size in tiles of the smallest features of the coastline
+const NOISE_SCALE: f32 = 4.0;
+const NOISE_OCTAVES: u32 = 3;
+// Shares of the land, from the highest ground down, that become mountains and hills
+const MOUNTAIN_SHARE: f64 = 0.1;
+const HILL_SHARE: f64 = 0.25;
+// Island centres are picked among this many candidates, the one farthest from the others wins
+const CENTER_CANDIDATES: u32 = 16;
+
+// How `generate_islands` shapes a map. The same settings always give the same map.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IslandSettings {
+    pub seed: u64,
+    // Share of the tiles that become land, between 0 and 1
+    pub land_ratio: f64,
+    // Number of islands the land is spread over. Islands can merge or break up, so a map
+    // may end up with a few more or fewer.
+    pub island_count: u32,
+    // Width in tiles of the shallow water along the coasts, 0 for none
+    pub shallows: u32,
+}
+
+impl Default for IslandSettings {
+    fn default() -> Self {
+        IslandSettings {
+            seed: 0,
+            land_ratio: 0.35,
+            island_count: 3,
+            shallows: 1,
+        }
+    }
+}
+
+// A `width` x `height` map (see `Map::new`) of islands in deep water, see `add_islands`
+pub fn generate_islands(width: u32, height: u32, settings: &IslandSettings) -> Map {
+    add_islands(Map::new(width, height), settings)
+}
+
+// Repaints every tile of `map` as islands in deep water, whatever its shape. Every tile gets
+// a height from its distance to the nearest island centre roughened by noise; the highest
+// tiles become land, the highest land mountains and hills. Buildings are left in place.
+pub fn add_islands(mut map: Map, settings: &IslandSettings) -> Map {
+    let Some(bounds) = map.world_bounds() else {
+        return map;
+    };
+    let mut rng = SeededRng::new(settings.seed);
+    let positions = map
+        .tiles()
+        .iter()
+        .map(|tile| map.tile_to_world_pos(tile))
+        .collect::<Vec<_>>();
+
+    let land_count =
+        (positions.len() as f64 * settings.land_ratio.clamp(0.0, 1.0)).round() as usize;
+    let island_count = settings.island_count.max(1);
+    let centers = island_centers(bounds, island_count, &mut rng);
+    // Large enough that the islands together cover about twice the land, noise and the
+    // land ratio decide the actual coastline
+    let area_per_tile = 3.0 * 3f32.sqrt() / 2.0 * map.hex_size().powi(2);
+    let radius = (2.0 * land_count as f32 * area_per_tile
+        / (island_count as f32 * std::f32::consts::PI))
+        .sqrt()
+        .max(map.hex_size());
+    let noise_seed = rng.next_u64();
+    let heights = positions
+        .iter()
+        .map(|&(x, y)| {
+            let distance = centers
+                .iter()
+                .map(|&(cx, cy)| (x - cx).hypot(y - cy))
+                .fold(f32::INFINITY, f32::min);
+            let falloff = (1.0 - distance / radius).max(0.0);
+            let noise = fractal_noise(noise_seed, x / NOISE_SCALE, y / NOISE_SCALE);
+            // A little noise everywhere decides where land goes once the islands are full
+            falloff * (0.5 + noise) + noise * 0.01
+        })
+        .collect::<Vec<_>>();
+
+    // Highest first, ties broken by position in the map so the order never depends on the sort
+    let mut order = (0..heights.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| heights[b].total_cmp(&heights[a]).then(a.cmp(&b)));
+    let mountains = (land_count as f64 * MOUNTAIN_SHARE).round() as usize;
+    let hills = mountains + (land_count as f64 * HILL_SHARE).round() as usize;
+    for (rank, &index) in order.iter().enumerate() {
+        map.tiles_mut()[index].terrain = match rank {
+            rank if rank < mountains => Terrain::Mountains,
+            rank if rank < hills => Terrain::Hills,
+            rank if rank < land_count => Terrain::Plains,
+            _ => Terrain::DeepWater,
+        };
+    }
+
+    let land = map
+        .tiles()
+        .iter()
+        .filter(|tile| tile.terrain.elevation() != Elevation::Water)
+        .map(|tile| tile.position())
+        .collect::<Vec<_>>();
+    let shallows = land
+        .iter()
+        .flat_map(|&hex| map.range(hex, settings.shallows))
+        .filter(|tile| tile.terrain == Terrain::DeepWater)
+        .map(|tile| tile.position())
+        .collect::<HashSet<Hex>>();
+    for hex in shallows {
+        if let Some(tile) = map.tile_at_mut(hex) {
+            tile.terrain = Terrain::ShallowWater;
+        }
+    }
+    map
+}
+
+// Spread out points within `bounds`, away from the edges of the map
+fn island_centers(bounds: WorldBounds, count: u32, rng: &mut SeededRng) -> Vec<(f32, f32)> {
+    let (min_x, min_y) = bounds.min;
+    let (max_x, max_y) = bounds.max;
+    let margin_x = (max_x - min_x) * 0.15;
+    let margin_y = (max_y - min_y) * 0.15;
+    let mut centers: Vec<(f32, f32)> = Vec::new();
+    for _ in 0..count {
+        let candidates = (0..CENTER_CANDIDATES).map(|_| {
+            let x = min_x + margin_x + rng.next_f64() as f32 * (max_x - min_x - 2.0 * margin_x);
+            let y = min_y + margin_y + rng.next_f64() as f32 * (max_y - min_y - 2.0 * margin_y);
+            (x, y)
+        });
+        let distance_to_others = |&(x, y): &(f32, f32)| {
+            centers
+                .iter()
+                .map(|&(cx, cy)| (x - cx).hypot(y - cy))
+                .fold(f32::INFINITY, f32::min)
+        };
+        let best = candidates
+            .map(|candidate| (distance_to_others(&candidate), candidate))
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+        centers.extend(best.map(|(_, center)| center));
+    }
+    centers
+}
+
+// Smooth value noise in `[0, 1)`, octaves of halving size and strength
+fn fractal_noise(seed: u64, x: f32, y: f32) -> f32 {
+    let mut sum = 0.0;
+    let mut total = 0.0;
+    let mut strength = 1.0;
+    let mut frequency = 1.0;
+    for octave in 0..NOISE_OCTAVES {
+        sum += value_noise(
+            seed.wrapping_add(u64::from(octave)),
+            x * frequency,
+            y * frequency,
+        ) * strength;
+        total += strength;
+        strength *= 0.5;
+        frequency *= 2.0;
+    }
+    sum / total
+}
+
+// Random values on the integer lattice, interpolated in between
+fn value_noise(seed: u64, x: f32, y: f32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, ty) = (smooth(x - x0), smooth(y - y0));
+    let (ix, iy) = (x0 as i64, y0 as i64);
+    let corner = |dx: i64, dy: i64| lattice_value(seed, ix + dx, iy + dy);
+    let top = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * tx;
+    let bottom = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * tx;
+    top + (bottom - top) * ty
+}
+
+fn lattice_value(seed: u64, x: i64, y: i64) -> f32 {
+    let key = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    SeededRng::new(key).next_f64() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{HexOrientation, MapShape};
+    use rstest::rstest;
+
+    fn terrains(map: &Map) -> Vec<Terrain> {
+        map.tiles().iter().map(|tile| tile.terrain).collect()
+    }
+
+    fn count(map: &Map, terrain: Terrain) -> usize {
+        map.tiles()
+            .iter()
+            .filter(|tile| tile.terrain == terrain)
+            .count()
+    }
+
+    #[test]
+    fn test_same_seed_same_map() {
+        let settings = IslandSettings {
+            seed: 42,
+            ..Default::default()
+        };
+        let a = generate_islands(30, 20, &settings);
+        let b = generate_islands(30, 20, &settings);
+        assert_eq!(terrains(&a), terrains(&b));
+
+        let other = IslandSettings {
+            seed: 43,
+            ..Default::default()
+        };
+        assert_ne!(terrains(&a), terrains(&generate_islands(30, 20, &other)));
+    }
+
+    #[rstest]
+    #[case(0.0)]
+    #[case(0.2)]
+    #[case(0.35)]
+    #[case(0.6)]
+    #[case(1.0)]
+    fn test_land_ratio(#[case] land_ratio: f64) {
+        let settings = IslandSettings {
+            seed: 7,
+            land_ratio,
+            ..Default::default()
+        };
+        let sut = generate_islands(30, 20, &settings);
+        let land = count(&sut, Terrain::Plains)
+            + count(&sut, Terrain::Hills)
+            + count(&sut, Terrain::Mountains);
+        let expected = (sut.tiles().len() as f64 * land_ratio).round() as usize;
+        assert_eq!(land, expected);
+        assert_eq!(sut.tiles().len(), Map::new(30, 20).tiles().len());
+    }
+
+    #[test]
+    fn test_all_terrains_are_used() {
+        let sut = generate_islands(40, 40, &IslandSettings::default());
+        for terrain in Terrain::ALL {
+            assert!(count(&sut, terrain) > 0, "no {:?}", terrain);
+        }
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(1)]
+    #[case(2)]
+    fn test_shallows_line_the_coast(#[case] shallows: u32) {
+        let settings = IslandSettings {
+            seed: 3,
+            shallows,
+            ..Default::default()
+        };
+        let sut = generate_islands(30, 30, &settings);
+        let is_land = |hex: Hex| {
+            sut.tile_at(hex)
+                .is_some_and(|tile| tile.terrain.elevation() != Elevation::Water)
+        };
+        for tile in sut.tiles() {
+            let near_land = sut
+                .range(tile.position(), shallows)
+                .any(|t| is_land(t.position()));
+            match tile.terrain {
+                Terrain::ShallowWater => assert!(near_land),
+                Terrain::DeepWater => assert!(!near_land),
+                _ => {}
+            }
+        }
+        assert_eq!(count(&sut, Terrain::ShallowWater) > 0, shallows > 0);
+    }
+
+    #[rstest]
+    #[case(MapShape::Hexagon { radius: 12 }, HexOrientation::Pointy)]
+    #[case(MapShape::Rectangle { width: 30, height: 20 }, HexOrientation::Flat)]
+    #[case(MapShape::Parallelogram { width: 25, height: 20 }, HexOrientation::Flat)]
+    fn test_islands_on_other_shapes(#[case] shape: MapShape, #[case] orientation: HexOrientation) {
+        let map = Map::with_shape(shape, orientation);
+        let positions = map
+            .tiles()
+            .iter()
+            .map(|tile| tile.position())
+            .collect::<Vec<_>>();
+
+        let settings = IslandSettings::default();
+
+        let sut = add_islands(map, &settings);
+
+        let after = sut
+            .tiles()
+            .iter()
+            .map(|tile| tile.position())
+            .collect::<Vec<_>>();
+        assert_eq!(after, positions);
+        let water = count(&sut, Terrain::DeepWater) + count(&sut, Terrain::ShallowWater);
+        let expected = (positions.len() as f64 * settings.land_ratio).round() as usize;
+        assert_eq!(sut.tiles().len() - water, expected);
+    }
+
+    #[rstest]
+    #[case(0, 0)]
+    #[case(0, 5)]
+    #[case(1, 1)]
+    fn test_tiny_maps(#[case] width: u32, #[case] height: u32) {
+        let sut = generate_islands(width, height, &IslandSettings::default());
+        assert_eq!(sut.tiles().len(), Map::new(width, height).tiles().len());
+    }
+}
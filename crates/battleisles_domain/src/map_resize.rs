@@ -0,0 +1,333 @@
+use crate::map::{Hex, Map, MapShape, OffsetConvention, Terrain, Tile};
+use std::collections::{HashMap, HashSet};
+
+// The side or corner of a map that stays in place when it is resized
+#[derive(PartialEq, Clone, Debug, Copy, Eq, Hash)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    // Row by row, as laid out in a 3x3 picker
+    pub const ALL: [Anchor; 9] = [
+        Anchor::TopLeft,
+        Anchor::Top,
+        Anchor::TopRight,
+        Anchor::Left,
+        Anchor::Center,
+        Anchor::Right,
+        Anchor::BottomLeft,
+        Anchor::Bottom,
+        Anchor::BottomRight,
+    ];
+
+    // Share of the change in size, in halves, added before the existing tiles horizontally
+    // and vertically
+    fn halves(self) -> (i32, i32) {
+        let index = Anchor::ALL
+            .iter()
+            .position(|anchor| *anchor == self)
+            .unwrap() as i32;
+        (index % 3, index / 3)
+    }
+}
+
+impl Map {
+    // Turns the map into a `width` x `height` rectangle (see `resized_shape`) of the same
+    // orientation. The existing tiles keep their terrain and buildings and are placed at the
+    // `anchor` side, new tiles get `fill`. Returns the tiles that no longer fit, at their old
+    // positions; none unless the map shrinks.
+    pub fn resize(&mut self, width: u32, height: u32, anchor: Anchor, fill: Terrain) -> Vec<Tile> {
+        let delta = self.resize_offset(width, height, anchor);
+        self.resize_and_move(width, height, delta, fill)
+    }
+
+    // Like resize, with the existing tiles moved by `delta` instead of to an anchor. Tiles
+    // moved off the map are dropped, even if it grows.
+    pub fn resize_and_move(
+        &mut self,
+        width: u32,
+        height: u32,
+        delta: Hex,
+        fill: Terrain,
+    ) -> Vec<Tile> {
+        let positions = Map::with_shape(self.resized_shape(width, height), self.orientation())
+            .tiles
+            .into_iter()
+            .map(|tile| tile.position)
+            .collect();
+        self.relocate(positions, delta, fill, (width, height))
+    }
+
+    // Full rectangles stay full, any other map becomes a trimmed rectangle
+    pub fn resized_shape(&self, width: u32, height: u32) -> MapShape {
+        // Without a shifted row (column) there is no telling trimmed and full apart
+        let shifted_lines = match self.offset_convention() {
+            OffsetConvention::OddR => self.height() > 1,
+            OffsetConvention::OddQ => self.width() > 1,
+        };
+        let area = self.width() as usize * self.height() as usize;
+        match shifted_lines && self.tiles.len() == area {
+            true => MapShape::Rectangle { width, height },
+            false => MapShape::TrimmedRectangle { width, height },
+        }
+    }
+
+    // How far `resize` moves the existing tiles
+    pub fn resize_offset(&self, width: u32, height: u32, anchor: Anchor) -> Hex {
+        let Some(((min_col, min_row), _)) = self.offset_bounds() else {
+            return Hex::ZERO;
+        };
+        let (halves_x, halves_y) = anchor.halves();
+        let mut col = ((width as i32 - self.width() as i32) * halves_x).div_euclid(2);
+        let mut row = ((height as i32 - self.height() as i32) * halves_y).div_euclid(2);
+        // Moving by an odd number of rows (columns of flat-top hexes) would turn long lines
+        // into short ones, so stay on the same parity, a line closer to the top (left)
+        match self.offset_convention() {
+            OffsetConvention::OddR => row -= (row - min_row).rem_euclid(2),
+            OffsetConvention::OddQ => col -= (col - min_col).rem_euclid(2),
+        }
+        self.offset_to_hex(col, row) - self.offset_to_hex(min_col, min_row)
+    }
+
+    // Moves the terrain and buildings by `dq`, `dr` while the map keeps its shape. Tiles
+    // left empty get `fill`. Returns the tiles moved off the map, at their old positions.
+    pub fn shift(&mut self, dq: i32, dr: i32, fill: Terrain) -> Vec<Tile> {
+        let positions = self.tiles.iter().map(|tile| tile.position).collect();
+        let size = (self.width(), self.height());
+        self.relocate(positions, Hex::new(dq, dr), fill, size)
+    }
+
+    // Replaces the tiles by ones at `positions`, taking over the tiles moved there by `delta`
+    fn relocate(
+        &mut self,
+        positions: Vec<Hex>,
+        delta: Hex,
+        fill: Terrain,
+        (width, height): (u32, u32),
+    ) -> Vec<Tile> {
+        let targets = positions.iter().copied().collect::<HashSet<_>>();
+        let mut moved = HashMap::new();
+        let mut dropped = Vec::new();
+        for tile in std::mem::take(&mut self.tiles) {
+            let position = tile.position + delta;
+            if targets.contains(&position) {
+                moved.insert(position, Tile { position, ..tile });
+            } else {
+                dropped.push(tile);
+            }
+        }
+        let tiles = positions
+            .into_iter()
+            .map(|position| {
+                moved.remove(&position).unwrap_or(Tile {
+                    position,
+                    terrain: fill,
+                    building: None,
+                })
+            })
+            .collect();
+        *self = Map::from_tiles(self.hex_size, self.layout.clone(), tiles).with_size(width, height);
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::building::{Building, BuildingKind};
+    use crate::map::HexOrientation;
+    use rstest::rstest;
+
+    // A 5x5 map with a plains tile at the given offset coordinates and a depot next to it
+    fn marked_map(col: i32, row: i32) -> Map {
+        let mut map = Map::new(5, 5);
+        let hex = map.offset_to_hex(col, row);
+        map.tile_at_mut(hex).unwrap().terrain = Terrain::Plains;
+        let depot = hex + Hex::new(1, 0);
+        map.tile_at_mut(depot).unwrap().building = Some(Building::new(BuildingKind::Depot, None));
+        map
+    }
+
+    fn offset_of(map: &Map, terrain: Terrain) -> Vec<(i32, i32)> {
+        map.tiles()
+            .iter()
+            .filter(|tile| tile.terrain == terrain)
+            .map(|tile| map.hex_to_offset(tile.position()))
+            .collect()
+    }
+
+    #[rstest]
+    #[case(Anchor::TopLeft, (2, 2))]
+    #[case(Anchor::Center, (4, 4))]
+    #[case(Anchor::BottomRight, (6, 6))]
+    #[case(Anchor::Bottom, (4, 6))]
+    fn test_growing_keeps_every_tile(#[case] anchor: Anchor, #[case] expected: (i32, i32)) {
+        let mut sut = marked_map(2, 2);
+
+        let dropped = sut.resize(9, 9, anchor, Terrain::Hills);
+
+        assert!(dropped.is_empty());
+        assert_eq!(sut.tiles().len(), Map::new(9, 9).tiles().len());
+        assert_eq!(offset_of(&sut, Terrain::Plains), vec![expected]);
+        assert_eq!(offset_of(&sut, Terrain::DeepWater).len(), 23 - 1);
+        assert_eq!(
+            sut.tiles().len() - 23,
+            offset_of(&sut, Terrain::Hills).len()
+        );
+        let depot = sut.tile_at(sut.offset_to_hex(expected.0, expected.1) + Hex::new(1, 0));
+        assert!(depot.unwrap().building.is_some());
+    }
+
+    #[rstest]
+    #[case(Anchor::TopLeft, Some((0, 0)))]
+    #[case(Anchor::BottomRight, None)]
+    fn test_cropping_drops_tiles(#[case] anchor: Anchor, #[case] expected: Option<(i32, i32)>) {
+        let mut sut = marked_map(0, 0);
+
+        let dropped = sut.resize(3, 3, anchor, Terrain::Hills);
+
+        let kept = Map::new(3, 3).tiles().len();
+        assert_eq!(sut.tiles().len(), kept);
+        assert_eq!(dropped.len(), 23 - kept);
+        assert!(offset_of(&sut, Terrain::Hills).is_empty());
+        assert_eq!(offset_of(&sut, Terrain::Plains).first().copied(), expected);
+        let dropped_plains = dropped.iter().any(|tile| tile.terrain == Terrain::Plains);
+        assert_eq!(dropped_plains, expected.is_none());
+        // At their old positions
+        let original = Map::new(5, 5);
+        assert!(dropped
+            .iter()
+            .all(|tile| original.contains(tile.position())));
+    }
+
+    #[rstest]
+    #[case(HexOrientation::Pointy, false)]
+    #[case(HexOrientation::Pointy, true)]
+    #[case(HexOrientation::Flat, false)]
+    #[case(HexOrientation::Flat, true)]
+    fn test_growing_never_drops_tiles(#[case] orientation: HexOrientation, #[case] trimmed: bool) {
+        for (width, height) in [(4, 4), (4, 5), (5, 4), (3, 6)] {
+            let shape = match trimmed {
+                true => MapShape::TrimmedRectangle { width, height },
+                false => MapShape::Rectangle { width, height },
+            };
+            for anchor in Anchor::ALL {
+                for (grow_x, grow_y) in [(0, 1), (1, 0), (1, 1), (2, 3), (3, 3)] {
+                    let mut sut = Map::with_shape(shape, orientation);
+                    let dropped =
+                        sut.resize(width + grow_x, height + grow_y, anchor, Terrain::Hills);
+                    assert!(
+                        dropped.is_empty(),
+                        "{:?} {}x{} grown by {}x{} at {:?}",
+                        shape,
+                        width,
+                        height,
+                        grow_x,
+                        grow_y,
+                        anchor
+                    );
+                    assert_eq!(
+                        offset_of(&sut, Terrain::DeepWater).len(),
+                        Map::with_shape(shape, orientation).tiles().len()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_resize_and_move() {
+        let mut sut = marked_map(3, 4);
+
+        // The marked tiles near the bottom right only fit in the smaller map when moved
+        let delta = sut.offset_to_hex(-2, -2) - sut.offset_to_hex(0, 0);
+        let dropped = sut.resize_and_move(3, 3, delta, Terrain::Hills);
+
+        assert_eq!(offset_of(&sut, Terrain::Plains), vec![(1, 2)]);
+        assert!(sut.tile_at_offset(2, 2).unwrap().building.is_some());
+        assert_eq!(sut.tiles().len() + dropped.len(), 23);
+        assert!(offset_of(&sut, Terrain::Hills).is_empty());
+    }
+
+    #[test]
+    fn test_resizing_an_empty_map() {
+        let mut sut = Map::new(0, 0);
+        assert!(sut.resize(3, 2, Anchor::Center, Terrain::Plains).is_empty());
+        assert_eq!(
+            offset_of(&sut, Terrain::Plains).len(),
+            Map::new(3, 2).tiles().len()
+        );
+    }
+
+    #[rstest]
+    #[case(1, 0, (3, 2), 5)]
+    #[case(-1, 0, (1, 2), 5)]
+    #[case(0, 2, (3, 4), 12)]
+    fn test_shift(
+        #[case] dq: i32,
+        #[case] dr: i32,
+        #[case] expected: (i32, i32),
+        #[case] expected_dropped: usize,
+    ) {
+        let mut sut = marked_map(2, 2);
+
+        let dropped = sut.shift(dq, dr, Terrain::Hills);
+
+        assert_eq!(sut.tiles().len(), 23);
+        assert_eq!(offset_of(&sut, Terrain::Plains), vec![expected]);
+        assert_eq!(dropped.len(), expected_dropped);
+        assert_eq!(offset_of(&sut, Terrain::Hills).len(), expected_dropped);
+        let depot = sut.tile_at(sut.offset_to_hex(expected.0, expected.1) + Hex::new(1, 0));
+        assert!(depot.unwrap().building.is_some());
+    }
+
+    #[test]
+    fn test_size_after_resize_and_shift() {
+        // Odd rows of a single column map are empty, the size tells where the map ends
+        let mut sut = Map::new(1, 4);
+        sut.tiles_mut()[1].terrain = Terrain::Plains;
+
+        assert!(sut.resize(1, 6, Anchor::Bottom, Terrain::Hills).is_empty());
+        assert_eq!((sut.width(), sut.height()), (1, 6));
+        assert_eq!(offset_of(&sut, Terrain::Plains), vec![(0, 4)]);
+
+        sut.shift(0, 0, Terrain::Hills);
+        assert_eq!((sut.width(), sut.height()), (1, 6));
+    }
+
+    #[rstest]
+    #[case(MapShape::TrimmedRectangle { width: 4, height: 4 }, HexOrientation::Flat, 4 * 6 - 2)]
+    #[case(MapShape::Rectangle { width: 4, height: 4 }, HexOrientation::Pointy, 4 * 6)]
+    #[case(MapShape::Rectangle { width: 4, height: 4 }, HexOrientation::Flat, 4 * 6)]
+    #[case(MapShape::Hexagon { radius: 2 }, HexOrientation::Flat, 4 * 6 - 2)]
+    fn test_resize_keeps_the_orientation(
+        #[case] shape: MapShape,
+        #[case] orientation: HexOrientation,
+        #[case] expected_tile_count: usize,
+    ) {
+        let mut sut = Map::with_shape(shape, orientation);
+
+        sut.resize(4, 6, Anchor::TopLeft, Terrain::Plains);
+
+        assert_eq!(sut.orientation(), orientation);
+        assert_eq!(sut.tiles().len(), expected_tile_count);
+        assert_eq!((sut.width(), sut.height()), (4, 6));
+    }
+
+    #[test]
+    fn test_anchor_halves() {
+        assert_eq!(Anchor::TopLeft.halves(), (0, 0));
+        assert_eq!(Anchor::Center.halves(), (1, 1));
+        assert_eq!(Anchor::Right.halves(), (2, 1));
+        assert_eq!(Anchor::Bottom.halves(), (1, 2));
+    }
+}
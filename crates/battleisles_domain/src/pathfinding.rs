@@ -0,0 +1,299 @@
+use crate::map::{Hex, Map, Terrain, Tile};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+// How a unit moves, which decides what each terrain costs it
+#[derive(PartialEq, Clone, Debug, Copy, Eq, Hash, Serialize, Deserialize)]
+pub enum MovementClass {
+    Land,
+    Tracked,
+    Naval,
+    Air,
+}
+
+impl MovementClass {
+    // Movement points needed to enter a tile of `terrain`, or `None` if it is impassable
+    pub fn cost(self, terrain: Terrain) -> Option<u32> {
+        use MovementClass::*;
+        use Terrain::*;
+        match (self, terrain) {
+            (Land, Plains) => Some(1),
+            (Land, Hills) => Some(2),
+            (Land, Mountains) => Some(3),
+            (Tracked, Plains) => Some(1),
+            (Tracked, Hills) => Some(2),
+            (Naval, DeepWater) => Some(1),
+            (Naval, ShallowWater) => Some(2),
+            (Air, _) => Some(1),
+            _ => None,
+        }
+    }
+
+    // Like `cost`, but a building that services this class makes its tile enterable at
+    // cost 1 whatever the terrain, so e.g. ships can dock in a harbour on the coast
+    pub fn tile_cost(self, tile: &Tile) -> Option<u32> {
+        match tile.building {
+            Some(building) if building.kind.admits(self) => Some(1),
+            _ => self.cost(tile.terrain),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Path {
+    // Every hex along the way, starting with the origin and ending with the destination
+    pub hexes: Vec<Hex>,
+    pub cost: u32,
+}
+
+// Result of a movement flood fill: every hex reachable within the budget and how to get there
+#[derive(Clone, Debug)]
+pub struct Reachable {
+    origin: Hex,
+    costs: HashMap<Hex, u32>,
+    came_from: HashMap<Hex, Hex>,
+}
+
+impl Reachable {
+    pub fn contains(&self, hex: Hex) -> bool {
+        self.costs.contains_key(&hex)
+    }
+
+    pub fn cost_to(&self, hex: Hex) -> Option<u32> {
+        self.costs.get(&hex).copied()
+    }
+
+    // Reachable hexes (including the origin) with the cost to reach them
+    pub fn iter(&self) -> impl Iterator<Item = (Hex, u32)> + '_ {
+        self.costs.iter().map(|(hex, cost)| (*hex, *cost))
+    }
+
+    pub fn path_to(&self, hex: Hex) -> Option<Path> {
+        let cost = self.cost_to(hex)?;
+        Some(Path {
+            hexes: walk_back(&self.came_from, self.origin, hex),
+            cost,
+        })
+    }
+}
+
+// Shortest path from `from` to `to` for the given movement class, using A*
+pub fn find_path(map: &Map, class: MovementClass, from: Hex, to: Hex) -> Option<Path> {
+    if !map.contains(from) {
+        return None;
+    }
+    if from != to {
+        class.tile_cost(map.tile_at(to)?)?;
+    }
+
+    // Every passable terrain costs at least one point, so hex distance never overestimates
+    let heuristic = |hex: Hex| hex.unsigned_distance_to(to);
+    let mut costs = HashMap::from([(from, 0)]);
+    let mut came_from = HashMap::new();
+    let mut open = BinaryHeap::from([Reverse((heuristic(from), from.x, from.y))]);
+
+    while let Some(Reverse((_, x, y))) = open.pop() {
+        let current = Hex::new(x, y);
+        if current == to {
+            return Some(Path {
+                hexes: walk_back(&came_from, from, to),
+                cost: costs[&to],
+            });
+        }
+        let current_cost = costs[&current];
+        for neighbor in map.neighbors(current) {
+            let Some(step) = class.tile_cost(neighbor) else {
+                continue;
+            };
+            let hex = neighbor.position();
+            let cost = current_cost + step;
+            if costs.get(&hex).is_some_and(|known| *known <= cost) {
+                continue;
+            }
+            costs.insert(hex, cost);
+            came_from.insert(hex, current);
+            open.push(Reverse((cost + heuristic(hex), hex.x, hex.y)));
+        }
+    }
+    None
+}
+
+// Every hex reachable from `from` spending at most `budget` movement points (Dijkstra)
+pub fn reachable(map: &Map, class: MovementClass, from: Hex, budget: u32) -> Reachable {
+    let mut costs = HashMap::new();
+    let mut came_from = HashMap::new();
+    if map.contains(from) {
+        costs.insert(from, 0);
+    }
+    let mut open = BinaryHeap::from([Reverse((0, from.x, from.y))]);
+
+    while let Some(Reverse((current_cost, x, y))) = open.pop() {
+        let current = Hex::new(x, y);
+        if costs
+            .get(&current)
+            .is_none_or(|known| *known < current_cost)
+        {
+            continue; // stale entry
+        }
+        for neighbor in map.neighbors(current) {
+            let Some(step) = class.tile_cost(neighbor) else {
+                continue;
+            };
+            let hex = neighbor.position();
+            let cost = current_cost + step;
+            if cost > budget || costs.get(&hex).is_some_and(|known| *known <= cost) {
+                continue;
+            }
+            costs.insert(hex, cost);
+            came_from.insert(hex, current);
+            open.push(Reverse((cost, hex.x, hex.y)));
+        }
+    }
+
+    Reachable {
+        origin: from,
+        costs,
+        came_from,
+    }
+}
+
+fn walk_back(came_from: &HashMap<Hex, Hex>, from: Hex, to: Hex) -> Vec<Hex> {
+    let mut hexes = vec![to];
+    let mut current = to;
+    while current != from {
+        current = came_from[&current];
+        hexes.push(current);
+    }
+    hexes.reverse();
+    hexes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::building::{Building, BuildingKind};
+    use rstest::rstest;
+
+    // 5x5 map of plains with a column of the given terrain at offset column 2,
+    // leaving a gap in the last row
+    fn map_with_wall(wall: Terrain) -> Map {
+        let mut map = Map::new(5, 5);
+        map.tiles_mut()
+            .iter_mut()
+            .for_each(|tile| tile.terrain = Terrain::Plains);
+        for row in 0..4 {
+            let hex = map.tile_at_offset(2, row).unwrap().position();
+            map.tile_at_mut(hex).unwrap().terrain = wall;
+        }
+        map
+    }
+
+    fn offset(map: &Map, col: i32, row: i32) -> Hex {
+        map.tile_at_offset(col, row).unwrap().position()
+    }
+
+    #[rstest]
+    #[case(MovementClass::Land, Terrain::Plains, Some(1))]
+    #[case(MovementClass::Land, Terrain::Mountains, Some(3))]
+    #[case(MovementClass::Land, Terrain::DeepWater, None)]
+    #[case(MovementClass::Tracked, Terrain::Mountains, None)]
+    #[case(MovementClass::Naval, Terrain::ShallowWater, Some(2))]
+    #[case(MovementClass::Naval, Terrain::Plains, None)]
+    #[case(MovementClass::Air, Terrain::DeepWater, Some(1))]
+    fn test_movement_cost(
+        #[case] class: MovementClass,
+        #[case] terrain: Terrain,
+        #[case] expected: Option<u32>,
+    ) {
+        assert_eq!(class.cost(terrain), expected);
+    }
+
+    #[rstest]
+    #[case(MovementClass::Land, Terrain::DeepWater, Some(9))] // around the wall through the gap
+    #[case(MovementClass::Land, Terrain::Mountains, Some(6))] // straight over the mountain
+    #[case(MovementClass::Tracked, Terrain::Mountains, Some(9))]
+    #[case(MovementClass::Air, Terrain::DeepWater, Some(4))]
+    #[case(MovementClass::Naval, Terrain::DeepWater, None)]
+    fn test_find_path(
+        #[case] class: MovementClass,
+        #[case] wall: Terrain,
+        #[case] expected_cost: Option<u32>,
+    ) {
+        let map = map_with_wall(wall);
+        let from = offset(&map, 0, 0);
+        let to = offset(&map, 4, 0);
+
+        let sut = find_path(&map, class, from, to);
+
+        assert_eq!(sut.as_ref().map(|path| path.cost), expected_cost);
+        if let Some(path) = sut {
+            assert_eq!(path.hexes.first(), Some(&from));
+            assert_eq!(path.hexes.last(), Some(&to));
+            path.hexes.windows(2).for_each(|step| {
+                assert_eq!(step[0].unsigned_distance_to(step[1]), 1);
+                assert!(class.cost(map.tile_at(step[1]).unwrap().terrain).is_some());
+            });
+            let cost: u32 = path.hexes[1..]
+                .iter()
+                .map(|hex| class.cost(map.tile_at(*hex).unwrap().terrain).unwrap())
+                .sum();
+            assert_eq!(cost, path.cost);
+        }
+    }
+
+    #[rstest]
+    #[case(Some(BuildingKind::Harbour), Some(2))]
+    #[case(Some(BuildingKind::Factory), None)]
+    #[case(None, None)]
+    fn test_ships_dock_in_harbours(
+        #[case] building: Option<BuildingKind>,
+        #[case] expected_cost: Option<u32>,
+    ) {
+        // Deep water with a coastal plains tile at the east end of the first row
+        let mut map = Map::new(3, 1);
+        let coast = offset(&map, 2, 0);
+        let tile = map.tile_at_mut(coast).unwrap();
+        tile.terrain = Terrain::Plains;
+        tile.building = building.map(|kind| Building::new(kind, None));
+
+        let sut = find_path(&map, MovementClass::Naval, offset(&map, 0, 0), coast);
+
+        assert_eq!(sut.map(|path| path.cost), expected_cost);
+    }
+
+    #[test]
+    fn test_find_path_to_self() {
+        let map = map_with_wall(Terrain::DeepWater);
+        let hex = offset(&map, 1, 1);
+        let sut = find_path(&map, MovementClass::Land, hex, hex).unwrap();
+        assert_eq!(sut.hexes, vec![hex]);
+        assert_eq!(sut.cost, 0);
+    }
+
+    #[rstest]
+    #[case(0, 1)]
+    #[case(1, 3)]
+    #[case(2, 6)]
+    #[case(100, 19)] // everything but the four wall tiles
+    fn test_reachable(#[case] budget: u32, #[case] expected_count: usize) {
+        let map = map_with_wall(Terrain::DeepWater);
+        let from = offset(&map, 0, 0);
+
+        let sut = reachable(&map, MovementClass::Land, from, budget);
+
+        assert_eq!(sut.iter().count(), expected_count);
+        assert_eq!(sut.cost_to(from), Some(0));
+        sut.iter().for_each(|(hex, cost)| {
+            assert!(cost <= budget);
+            let path = sut.path_to(hex).unwrap();
+            assert_eq!(path.cost, cost);
+            assert_eq!(
+                find_path(&map, MovementClass::Land, from, hex)
+                    .unwrap()
+                    .cost,
+                cost
+            );
+        });
+    }
+}
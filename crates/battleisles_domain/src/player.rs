@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(PartialEq, Clone, Debug, Copy, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PlayerId(pub u8);
+
+#[derive(PartialEq, Clone, Debug, Copy, Eq, Hash, Serialize, Deserialize)]
+pub enum Faction {
+    Drull,
+    Kai,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Player {
+    pub id: PlayerId,
+    pub name: String,
+    pub faction: Faction,
+    // Currency for producing units, collected from owned buildings every turn
+    pub energy: u32,
+}
+
+impl Player {
+    pub fn new(id: PlayerId, name: impl Into<String>, faction: Faction) -> Self {
+        Player {
+            id,
+            name: name.into(),
+            faction,
+            energy: 0,
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use crate::map::Hex;
+use crate::pathfinding::MovementClass;
+use crate::player::PlayerId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// Key of a unit type in the catalogue, e.g. "infantry"
+#[derive(PartialEq, Clone, Debug, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UnitTypeId(pub String);
+
+impl fmt::Display for UnitTypeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// What kind of target a unit is when it is attacked
+#[derive(PartialEq, Clone, Debug, Copy, Eq, Hash, Serialize, Deserialize)]
+pub enum TargetClass {
+    Ground,
+    Naval,
+    Air,
+}
+
+impl From<MovementClass> for TargetClass {
+    fn from(class: MovementClass) -> Self {
+        match class {
+            MovementClass::Land | MovementClass::Tracked => TargetClass::Ground,
+            MovementClass::Naval => TargetClass::Naval,
+            MovementClass::Air => TargetClass::Air,
+        }
+    }
+}
+
+// One value per target class. For attack it is the strength against that kind of target
+// (0 means it cannot be attacked at all), for defence the strength against attackers of that kind.
+#[derive(PartialEq, Clone, Debug, Copy, Default, Serialize, Deserialize)]
+pub struct TargetValues {
+    pub ground: u32,
+    pub naval: u32,
+    pub air: u32,
+}
+
+impl TargetValues {
+    pub fn get(&self, class: TargetClass) -> u32 {
+        match class {
+            TargetClass::Ground => self.ground,
+            TargetClass::Naval => self.naval,
+            TargetClass::Air => self.air,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct TransportCapacity {
+    pub slots: u32,
+    pub carries: Vec<MovementClass>,
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct UnitType {
+    pub id: UnitTypeId,
+    pub name: String,
+    pub movement_class: MovementClass,
+    pub movement_points: u32,
+    pub attack: TargetValues,
+    pub defence: TargetValues,
+    pub min_range: u32,
+    pub max_range: u32,
+    pub ammo: u32,
+    // `None` for units that do not use fuel
+    pub fuel: Option<u32>,
+    pub vision: u32,
+    // Energy needed to produce the unit
+    pub cost: u32,
+    #[serde(default)]
+    pub transport: Option<TransportCapacity>,
+    // Whether the unit takes over enemy and neutral buildings it stands on
+    #[serde(default)]
+    pub can_capture: bool,
+}
+
+impl UnitType {
+    pub fn target_class(&self) -> TargetClass {
+        self.movement_class.into()
+    }
+
+    pub fn can_attack(&self, target: TargetClass) -> bool {
+        self.ammo > 0 && self.attack.get(target) > 0
+    }
+
+    pub fn in_range(&self, distance: u32) -> bool {
+        (self.min_range..=self.max_range).contains(&distance)
+    }
+}
+
+#[derive(Deserialize)]
+struct UnitCatalogueFile {
+    unit_types: Vec<UnitType>,
+}
+
+#[derive(Debug)]
+pub enum CatalogueError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    DuplicateUnitType(UnitTypeId),
+    InvalidRange(UnitTypeId),
+}
+
+impl fmt::Display for CatalogueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogueError::Io(e) => write!(f, "I/O error: {e}"),
+            CatalogueError::Parse(e) => write!(f, "malformed unit catalogue: {e}"),
+            CatalogueError::DuplicateUnitType(id) => write!(f, "duplicate unit type {id}"),
+            CatalogueError::InvalidRange(id) => {
+                write!(
+                    f,
+                    "unit type {id} has a minimum range above its maximum range"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CatalogueError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CatalogueError::Io(e) => Some(e),
+            CatalogueError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for CatalogueError {
+    fn from(e: std::io::Error) -> Self {
+        CatalogueError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for CatalogueError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        CatalogueError::Parse(e)
+    }
+}
+
+// All unit types available in a game, loaded from a RON data file (see `assets/data/units.ron`)
+#[derive(Clone, Debug, Default)]
+pub struct UnitCatalogue {
+    unit_types: Vec<UnitType>,
+    index: HashMap<UnitTypeId, usize>,
+}
+
+impl UnitCatalogue {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CatalogueError> {
+        UnitCatalogue::from_ron_str(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_ron_str(s: &str) -> Result<Self, CatalogueError> {
+        let file: UnitCatalogueFile = ron::from_str(s)?;
+        UnitCatalogue::from_unit_types(file.unit_types)
+    }
+
+    pub fn from_unit_types(unit_types: Vec<UnitType>) -> Result<Self, CatalogueError> {
+        let mut index = HashMap::with_capacity(unit_types.len());
+        for (i, unit_type) in unit_types.iter().enumerate() {
+            if unit_type.min_range > unit_type.max_range {
+                return Err(CatalogueError::InvalidRange(unit_type.id.clone()));
+            }
+            if index.insert(unit_type.id.clone(), i).is_some() {
+                return Err(CatalogueError::DuplicateUnitType(unit_type.id.clone()));
+            }
+        }
+        Ok(UnitCatalogue { unit_types, index })
+    }
+
+    pub fn get(&self, id: &UnitTypeId) -> Option<&UnitType> {
+        self.index.get(id).map(|i| &self.unit_types[*i])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &UnitType> {
+        self.unit_types.iter()
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct UnitId(pub u32);
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Unit {
+    pub id: UnitId,
+    pub unit_type: UnitTypeId,
+    pub owner: PlayerId,
+    pub position: Hex,
+    // Remaining strength, from `Unit::MAX_HEALTH` down to 0 when destroyed
+    pub health: u32,
+    pub experience: u32,
+    pub ammo: u32,
+    pub fuel: Option<u32>,
+    // Per-turn flags, cleared when the owner's turn starts
+    pub has_moved: bool,
+    pub has_attacked: bool,
+}
+
+impl Unit {
+    pub const MAX_HEALTH: u32 = 10;
+    pub const MAX_EXPERIENCE: u32 = 5;
+
+    // A fresh unit at full health with full ammo and fuel
+    pub fn new(id: UnitId, unit_type: &UnitType, owner: PlayerId, position: Hex) -> Self {
+        Unit {
+            id,
+            unit_type: unit_type.id.clone(),
+            owner,
+            position,
+            health: Unit::MAX_HEALTH,
+            experience: 0,
+            ammo: unit_type.ammo,
+            fuel: unit_type.fuel,
+            has_moved: false,
+            has_attacked: false,
+        }
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.health == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn shipped_catalogue() -> UnitCatalogue {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../assets/data/units.ron");
+        UnitCatalogue::load(path).unwrap()
+    }
+
+    const INFANTRY: &str = "(
+        id: \"infantry\",
+        name: \"Infantry\",
+        movement_class: Land,
+        movement_points: 3,
+        attack: (ground: 4, naval: 0, air: 1),
+        defence: (ground: 3, naval: 3, air: 2),
+        min_range: 1,
+        max_range: 1,
+        ammo: 8,
+        fuel: None,
+        vision: 2,
+        cost: 100,
+    )";
+
+    #[rstest]
+    #[case("infantry", MovementClass::Land, true)]
+    #[case("light_tank", MovementClass::Tracked, false)]
+    #[case("artillery", MovementClass::Tracked, false)]
+    #[case("cruiser", MovementClass::Naval, false)]
+    #[case("fighter", MovementClass::Air, false)]
+    #[case("transport_ship", MovementClass::Naval, false)]
+    fn test_shipped_catalogue(
+        #[case] id: &str,
+        #[case] movement_class: MovementClass,
+        #[case] can_capture: bool,
+    ) {
+        let sut = shipped_catalogue();
+        let unit_type = sut.get(&UnitTypeId(id.to_owned())).unwrap();
+        assert_eq!(unit_type.movement_class, movement_class);
+        assert_eq!(unit_type.can_capture, can_capture);
+        assert!(unit_type.movement_points > 0);
+        assert!(unit_type.cost > 0);
+    }
+
+    #[test]
+    fn test_catalogue_from_ron() {
+        let text = format!("(unit_types: [{INFANTRY}])");
+        let sut = UnitCatalogue::from_ron_str(&text).unwrap();
+        let infantry = sut.get(&UnitTypeId("infantry".to_owned())).unwrap();
+
+        assert_eq!(infantry.target_class(), TargetClass::Ground);
+        assert!(infantry.can_attack(TargetClass::Air));
+        assert!(!infantry.can_attack(TargetClass::Naval));
+        assert!(infantry.in_range(1));
+        assert!(!infantry.in_range(2));
+        assert_eq!(infantry.transport, None);
+        assert!(!infantry.can_capture);
+        assert!(sut.get(&UnitTypeId("tank".to_owned())).is_none());
+    }
+
+    #[test]
+    fn test_duplicate_unit_type_is_rejected() {
+        let text = format!("(unit_types: [{INFANTRY}, {INFANTRY}])");
+        assert!(matches!(
+            UnitCatalogue::from_ron_str(&text),
+            Err(CatalogueError::DuplicateUnitType(_))
+        ));
+    }
+
+    #[test]
+    fn test_missing_cost_is_rejected() {
+        let text = format!("(unit_types: [{}])", INFANTRY.replace("cost: 100,", ""));
+        assert!(matches!(
+            UnitCatalogue::from_ron_str(&text),
+            Err(CatalogueError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_range_is_rejected() {
+        let text = format!(
+            "(unit_types: [{}])",
+            INFANTRY.replace("min_range: 1", "min_range: 2")
+        );
+        assert!(matches!(
+            UnitCatalogue::from_ron_str(&text),
+            Err(CatalogueError::InvalidRange(_))
+        ));
+    }
+
+    #[test]
+    fn test_new_unit_is_fully_supplied() {
+        let catalogue = shipped_catalogue();
+        let fighter = catalogue.get(&UnitTypeId("fighter".to_owned())).unwrap();
+
+        let sut = Unit::new(UnitId(7), fighter, PlayerId(1), Hex::new(2, 3));
+
+        assert_eq!(sut.unit_type, fighter.id);
+        assert_eq!(sut.health, Unit::MAX_HEALTH);
+        assert_eq!(sut.ammo, fighter.ammo);
+        assert_eq!(sut.fuel, fighter.fuel);
+        assert!(sut.fuel.is_some());
+        assert!(!sut.is_destroyed());
+    }
+}
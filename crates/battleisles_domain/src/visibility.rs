@@ -0,0 +1,147 @@
+use crate::map::{Hex, Map};
+use std::collections::HashSet;
+
+// How much a player knows about a tile
+#[derive(PartialEq, Clone, Debug, Copy, Eq, Hash)]
+pub enum TileVisibility {
+    // Never seen, neither terrain nor buildings are known
+    Unexplored,
+    // Seen before but not currently in sight, units there are not known
+    Remembered,
+    // Currently in sight of one of the player's units or buildings
+    Visible,
+}
+
+// Something that sees the tiles around it, i.e. a unit or a building
+#[derive(PartialEq, Clone, Debug, Copy)]
+pub struct Observer {
+    pub position: Hex,
+    pub vision: u32,
+    // Aircraft look over terrain that blocks sight on the ground
+    pub airborne: bool,
+}
+
+// What a single player sees now and has seen during the game
+#[derive(Clone, Debug, Default)]
+pub struct PlayerVisibility {
+    visible: HashSet<Hex>,
+    seen: HashSet<Hex>,
+}
+
+impl PlayerVisibility {
+    pub fn is_visible(&self, hex: Hex) -> bool {
+        self.visible.contains(&hex)
+    }
+
+    pub fn was_seen(&self, hex: Hex) -> bool {
+        self.seen.contains(&hex)
+    }
+
+    pub fn get(&self, hex: Hex) -> TileVisibility {
+        if self.is_visible(hex) {
+            TileVisibility::Visible
+        } else if self.was_seen(hex) {
+            TileVisibility::Remembered
+        } else {
+            TileVisibility::Unexplored
+        }
+    }
+
+    // The visibility of every tile in the order of `map.tiles()`
+    pub fn tiles(&self, map: &Map) -> Vec<TileVisibility> {
+        map.tiles()
+            .iter()
+            .map(|tile| self.get(tile.position()))
+            .collect()
+    }
+
+    // Replaces what is visible now and adds it to what has been seen
+    pub fn update(&mut self, map: &Map, observers: impl IntoIterator<Item = Observer>) {
+        self.visible = visible_hexes(map, observers);
+        self.seen.extend(self.visible.iter().copied());
+    }
+}
+
+// Hexes of the map within vision range of any observer that are in its line of sight
+// (see `Map::has_line_of_sight`), or all of them for airborne observers
+pub fn visible_hexes(map: &Map, observers: impl IntoIterator<Item = Observer>) -> HashSet<Hex> {
+    let mut visible = HashSet::new();
+    for observer in observers {
+        for tile in map.range(observer.position, observer.vision) {
+            let hex = tile.position();
+            if observer.airborne || map.has_line_of_sight(observer.position, hex) {
+                visible.insert(hex);
+            }
+        }
+    }
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Terrain;
+    use rstest::rstest;
+
+    // 7x1 strip of plains with a mountain at offset column 3
+    fn strip() -> Map {
+        let mut map = Map::new(7, 1);
+        map.tiles_mut()
+            .iter_mut()
+            .for_each(|tile| tile.terrain = Terrain::Plains);
+        map.tile_at_mut(Hex::new(3, 0)).unwrap().terrain = Terrain::Mountains;
+        map
+    }
+
+    fn observer(col: i32, vision: u32, airborne: bool) -> Observer {
+        Observer {
+            position: Hex::new(col, 0),
+            vision,
+            airborne,
+        }
+    }
+
+    #[rstest]
+    #[case(0, 2, false, vec![0, 1, 2])]
+    #[case(1, 5, false, vec![0, 1, 2, 3])] // the mountain is seen, not what lies behind it
+    #[case(1, 5, true, vec![0, 1, 2, 3, 4, 5, 6])]
+    #[case(3, 2, false, vec![1, 2, 3, 4, 5])] // standing on the mountain
+    #[case(6, 0, false, vec![6])]
+    fn test_visible_hexes(
+        #[case] col: i32,
+        #[case] vision: u32,
+        #[case] airborne: bool,
+        #[case] expected: Vec<i32>,
+    ) {
+        let map = strip();
+        let sut = visible_hexes(&map, [observer(col, vision, airborne)]);
+        let mut cols = sut.iter().map(|hex| hex.x).collect::<Vec<_>>();
+        cols.sort();
+        assert_eq!(cols, expected);
+    }
+
+    #[test]
+    fn test_seen_tiles_are_remembered() {
+        let map = strip();
+        let mut sut = PlayerVisibility::default();
+
+        sut.update(&map, [observer(0, 1, false)]);
+        sut.update(&map, [observer(6, 1, false)]);
+
+        assert_eq!(sut.get(Hex::new(0, 0)), TileVisibility::Remembered);
+        assert_eq!(sut.get(Hex::new(3, 0)), TileVisibility::Unexplored);
+        assert_eq!(sut.get(Hex::new(5, 0)), TileVisibility::Visible);
+        assert_eq!(
+            sut.tiles(&map),
+            vec![
+                TileVisibility::Remembered,
+                TileVisibility::Remembered,
+                TileVisibility::Unexplored,
+                TileVisibility::Unexplored,
+                TileVisibility::Unexplored,
+                TileVisibility::Visible,
+                TileVisibility::Visible,
+            ]
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use battleisles_bevy::map_model_plugin::{BuildingChanged, TerrainChanged};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MAX_RECENT_FILES: usize = 8;
+
+// The map currently being edited: where it lives on disk and whether it has unsaved edits
+#[derive(Resource, Default)]
+pub struct EditorDocument {
+    pub path: Option<PathBuf>,
+    pub dirty: bool,
+    // Most recent first
+    pub recent_files: Vec<PathBuf>,
+    // Where recent_files is kept between sessions, None to keep it for this session only
+    recent_files_path: Option<PathBuf>,
+}
+
+impl EditorDocument {
+    // Starts with the recent files listed at `recent_files_path`, leaving out files that
+    // were moved or deleted since. A missing or unreadable list is an empty one.
+    pub fn with_recent_files_at(recent_files_path: PathBuf) -> Self {
+        EditorDocument {
+            recent_files: load_recent_files(&recent_files_path),
+            recent_files_path: Some(recent_files_path),
+            ..Default::default()
+        }
+    }
+
+    pub fn display_name(&self) -> String {
+        self.path
+            .as_deref()
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Untitled".to_owned())
+    }
+
+    // Called after the map was generated from scratch
+    pub fn reset(&mut self) {
+        self.path = None;
+        self.dirty = false;
+    }
+
+    // Called after the map was successfully loaded from or written to `path`
+    pub fn set_saved_path(&mut self, path: PathBuf) {
+        self.recent_files.retain(|p| *p != path);
+        self.recent_files.insert(0, path.clone());
+        self.recent_files.truncate(MAX_RECENT_FILES);
+        self.save_recent_files();
+        self.path = Some(path);
+        self.dirty = false;
+    }
+
+    pub fn forget_recent(&mut self, path: &Path) {
+        self.recent_files.retain(|p| p != path);
+        self.save_recent_files();
+    }
+
+    // Failing to remember the recent files is not worth interrupting the user for
+    fn save_recent_files(&self) {
+        let Some(path) = &self.recent_files_path else {
+            return;
+        };
+        let pretty = ron::ser::PrettyConfig::default();
+        let result = ron::ser::to_string_pretty(&self.recent_files, pretty)
+            .map_err(|e| e.to_string())
+            .and_then(|text| {
+                let dir = path.parent().expect("the path is in a directory");
+                fs::create_dir_all(dir)
+                    .and_then(|()| fs::write(path, text))
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            println!("Failed to save the recent files: {}", e);
+        }
+    }
+}
+
+// A RON list of paths in the user's config directory, None where there is none
+pub fn recent_files_path() -> Option<PathBuf> {
+    Some(
+        dirs::config_dir()?
+            .join("battleisles")
+            .join("recent_files.ron"),
+    )
+}
+
+fn load_recent_files(path: &Path) -> Vec<PathBuf> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let mut recent_files = match ron::from_str::<Vec<PathBuf>>(&text) {
+        Ok(recent_files) => recent_files,
+        Err(e) => {
+            println!("Failed to read the recent files: {}", e);
+            return Vec::new();
+        }
+    };
+    recent_files.retain(|path| path.is_file());
+    recent_files.truncate(MAX_RECENT_FILES);
+    recent_files
+}
+
+pub fn track_edits_system(
+    mut terrain_edits: EventReader<TerrainChanged>,
+    mut building_edits: EventReader<BuildingChanged>,
+    mut document: ResMut<EditorDocument>,
+) {
+    let edits = terrain_edits.read().count() + building_edits.read().count();
+    if edits > 0 && !document.dirty {
+        document.dirty = true;
+    }
+}
+
+pub fn window_title_system(
+    document: Res<EditorDocument>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !document.is_changed() {
+        return;
+    }
+    let Ok(mut window) = windows.single_mut() else {
+        return;
+    };
+    let marker = if document.dirty { "*" } else { "" };
+    window.title = format!(
+        "Battle Isles Editor - {}{}",
+        document.display_name(),
+        marker
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A directory of its own for each test, as tests run in parallel
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("battleisles_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_recent_files_round_trip() {
+        let dir = temp_dir("recent_files");
+        let (a, b) = (dir.join("a.ron"), dir.join("b.ron"));
+        fs::write(&a, "").unwrap();
+        fs::write(&b, "").unwrap();
+        // The config directory is created on the first save
+        let recent_files_path = dir.join("config").join("recent_files.ron");
+
+        let mut sut = EditorDocument::with_recent_files_at(recent_files_path.clone());
+        assert!(sut.recent_files.is_empty());
+        sut.set_saved_path(a.clone());
+        sut.set_saved_path(b.clone());
+        sut.set_saved_path(a.clone());
+
+        let reopened = EditorDocument::with_recent_files_at(recent_files_path.clone());
+        assert_eq!(reopened.recent_files, vec![a.clone(), b.clone()]);
+
+        // Files that are gone are left out
+        fs::remove_file(&a).unwrap();
+        let mut reopened = EditorDocument::with_recent_files_at(recent_files_path.clone());
+        assert_eq!(reopened.recent_files, vec![b.clone()]);
+
+        reopened.forget_recent(&b);
+        let reopened = EditorDocument::with_recent_files_at(recent_files_path);
+        assert!(reopened.recent_files.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unreadable_recent_files_are_ignored() {
+        let dir = temp_dir("bad_recent_files");
+        let recent_files_path = dir.join("recent_files.ron");
+        fs::write(&recent_files_path, "not a list").unwrap();
+
+        let sut = EditorDocument::with_recent_files_at(recent_files_path);
+
+        assert!(sut.recent_files.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_recent_files_are_kept_in_memory_by_default() {
+        let mut sut = EditorDocument::default();
+
+        for i in 0..MAX_RECENT_FILES + 2 {
+            sut.set_saved_path(PathBuf::from(format!("{i}.ron")));
+        }
+
+        assert_eq!(sut.recent_files.len(), MAX_RECENT_FILES);
+        assert_eq!(
+            sut.recent_files[0],
+            PathBuf::from(format!("{}.ron", MAX_RECENT_FILES + 1))
+        );
+        assert_eq!(sut.recent_files_path, None);
+    }
+}
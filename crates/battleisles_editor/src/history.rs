@@ -0,0 +1,432 @@
+use crate::document::EditorDocument;
+use crate::{RedoEvent, UndoEvent};
+use battleisles_bevy::map_model::MapModel;
+use battleisles_bevy::map_model_plugin::{BuildingChanged, MapModelPlugin, TerrainChanged};
+use battleisles_domain::building::Building;
+use battleisles_domain::map::{Map, Terrain};
+use bevy::input::ButtonInput;
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+const DEFAULT_MAX_DEPTH: usize = 100;
+
+#[derive(Clone, Copy, Debug)]
+pub enum TileEdit {
+    Terrain {
+        index: usize,
+        previous: Terrain,
+        terrain: Terrain,
+    },
+    Building {
+        index: usize,
+        previous: Option<Building>,
+        building: Option<Building>,
+    },
+}
+
+impl TileEdit {
+    // Puts the tile back to its state before the edit, or after it when redoing
+    fn apply(
+        &self,
+        undo: bool,
+        map_model: &mut MapModel,
+        materials: &mut ResMut<Assets<StandardMaterial>>,
+        commands: &mut Commands,
+    ) {
+        match *self {
+            TileEdit::Terrain {
+                index,
+                previous,
+                terrain,
+            } => {
+                let terrain = if undo { previous } else { terrain };
+                map_model.set_tile_terrain(index, terrain, materials, commands);
+            }
+            TileEdit::Building {
+                index,
+                previous,
+                building,
+            } => {
+                let building = if undo { previous } else { building };
+                map_model.set_tile_building(index, building, materials, commands);
+            }
+        }
+    }
+}
+
+pub enum EditCommand {
+    // Tile-by-tile changes, e.g. a paint stroke, a fill or placing buildings
+    Tiles {
+        label: String,
+        edits: Vec<TileEdit>,
+    },
+    // Whole-map replacement, e.g. generate or resize. Boxed as maps are much larger than
+    // the other commands.
+    ReplaceMap {
+        label: String,
+        before: Box<Map>,
+        after: Box<Map>,
+    },
+}
+
+impl EditCommand {
+    pub fn label(&self) -> &str {
+        match self {
+            EditCommand::Tiles { label, .. } | EditCommand::ReplaceMap { label, .. } => label,
+        }
+    }
+}
+
+// Undo/redo stacks for the editor. Tile edits reported by the map model are collected
+// into a stroke while the mouse button is held and committed as a single command.
+#[derive(Resource)]
+pub struct EditHistory {
+    max_depth: usize,
+    undo_stack: Vec<EditCommand>,
+    redo_stack: Vec<EditCommand>,
+    stroke: Vec<TileEdit>,
+    // Set by the tool that made the stroke
+    stroke_label: Option<String>,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            stroke: Vec::new(),
+            stroke_label: None,
+        }
+    }
+}
+
+impl EditHistory {
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth.max(1);
+        self.trim();
+    }
+
+    pub fn push(&mut self, command: EditCommand) {
+        self.redo_stack.clear();
+        self.undo_stack.push(command);
+        self.trim();
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.stroke.clear();
+        self.stroke_label = None;
+    }
+
+    // Names the command the current stroke becomes, e.g. "Fill"
+    pub fn label_stroke(&mut self, label: &str) {
+        self.stroke_label = Some(label.to_owned());
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    // Oldest first
+    pub fn undo_entries(&self) -> impl Iterator<Item = &EditCommand> {
+        self.undo_stack.iter()
+    }
+
+    // Next redo first
+    pub fn redo_entries(&self) -> impl Iterator<Item = &EditCommand> {
+        self.redo_stack.iter().rev()
+    }
+
+    fn record(&mut self, edit: TileEdit) {
+        self.stroke.push(edit);
+    }
+
+    fn commit_stroke(&mut self) {
+        if self.stroke.is_empty() {
+            return;
+        }
+        let edits = std::mem::take(&mut self.stroke);
+        let label = self.stroke_label.take().unwrap_or_else(|| {
+            if edits
+                .iter()
+                .all(|edit| matches!(edit, TileEdit::Building { .. }))
+            {
+                "Place Building".to_owned()
+            } else {
+                "Paint".to_owned()
+            }
+        });
+        self.push(EditCommand::Tiles { label, edits });
+    }
+
+    fn trim(&mut self) {
+        if self.undo_stack.len() > self.max_depth {
+            let excess = self.undo_stack.len() - self.max_depth;
+            self.undo_stack.drain(..excess);
+        }
+    }
+}
+
+pub fn record_tile_changes_system(
+    mut terrain_changes: EventReader<TerrainChanged>,
+    mut building_changes: EventReader<BuildingChanged>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut history: ResMut<EditHistory>,
+) {
+    for change in terrain_changes.read() {
+        history.record(TileEdit::Terrain {
+            index: change.index,
+            previous: change.previous,
+            terrain: change.terrain,
+        });
+    }
+    for change in building_changes.read() {
+        history.record(TileEdit::Building {
+            index: change.index,
+            previous: change.previous,
+            building: change.building,
+        });
+    }
+    // A stroke lasts as long as the paint button is held
+    if !mouse.pressed(MouseButton::Left) {
+        history.commit_stroke();
+    }
+}
+
+pub fn undo_redo_shortcut_system(
+    mut contexts: EguiContexts,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut undo_events: EventWriter<UndoEvent>,
+    mut redo_events: EventWriter<RedoEvent>,
+) {
+    if contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if !ctrl {
+        return;
+    }
+    if keys.just_pressed(KeyCode::KeyY) || (shift && keys.just_pressed(KeyCode::KeyZ)) {
+        redo_events.write(RedoEvent);
+    } else if keys.just_pressed(KeyCode::KeyZ) {
+        undo_events.write(UndoEvent);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_undo_redo_events(
+    mut undo_events: EventReader<UndoEvent>,
+    mut redo_events: EventReader<RedoEvent>,
+    mut history: ResMut<EditHistory>,
+    mut map_model: Option<ResMut<MapModel>>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut document: ResMut<EditorDocument>,
+) {
+    let undo_count = undo_events.read().count();
+    let redo_count = redo_events.read().count();
+    if undo_count == 0 && redo_count == 0 {
+        return;
+    }
+    // Never undo past a stroke that is still being painted
+    history.commit_stroke();
+    let history = &mut *history;
+
+    let operations =
+        std::iter::repeat_n(true, undo_count).chain(std::iter::repeat_n(false, redo_count));
+    for undo in operations {
+        let (from, to) = if undo {
+            (&mut history.undo_stack, &mut history.redo_stack)
+        } else {
+            (&mut history.redo_stack, &mut history.undo_stack)
+        };
+        let Some(command) = from.pop() else {
+            continue;
+        };
+        document.dirty = true;
+
+        match &command {
+            EditCommand::Tiles { edits, .. } => {
+                if let Some(map_model) = map_model.as_mut() {
+                    if undo {
+                        for edit in edits.iter().rev() {
+                            edit.apply(true, map_model, &mut materials, &mut commands);
+                        }
+                    } else {
+                        for edit in edits {
+                            edit.apply(false, map_model, &mut materials, &mut commands);
+                        }
+                    }
+                }
+                to.push(command);
+            }
+            EditCommand::ReplaceMap { before, after, .. } => {
+                let map = Map::clone(if undo { before } else { after });
+                to.push(command);
+                if let Err(e) = MapModelPlugin::initialize_map_model(
+                    map,
+                    &mut commands,
+                    &mut meshes,
+                    &mut materials,
+                ) {
+                    println!("Failed to restore map: {:?}", e);
+                }
+                // The new map model only exists once commands are applied, so stop here
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use battleisles_domain::building::BuildingKind;
+    use rstest::rstest;
+
+    fn paint(index: usize) -> TileEdit {
+        TileEdit::Terrain {
+            index,
+            previous: Terrain::DeepWater,
+            terrain: Terrain::Plains,
+        }
+    }
+
+    fn place(index: usize) -> TileEdit {
+        TileEdit::Building {
+            index,
+            previous: None,
+            building: Some(Building::new(BuildingKind::Depot, None)),
+        }
+    }
+
+    fn command(label: &str) -> EditCommand {
+        EditCommand::Tiles {
+            label: label.to_owned(),
+            edits: vec![paint(0)],
+        }
+    }
+
+    fn undo_labels(history: &EditHistory) -> Vec<&str> {
+        history.undo_entries().map(EditCommand::label).collect()
+    }
+
+    #[rstest]
+    #[case(vec![paint(0), paint(1), paint(2)], "Paint")]
+    #[case(vec![place(0), place(1)], "Place Building")]
+    #[case(vec![place(0), paint(1)], "Paint")]
+    fn test_stroke_becomes_one_command(#[case] edits: Vec<TileEdit>, #[case] expected: &str) {
+        let mut sut = EditHistory::default();
+        let count = edits.len();
+
+        for edit in edits {
+            sut.record(edit);
+        }
+        // Nothing to undo while the stroke goes on
+        assert!(!sut.can_undo());
+        sut.commit_stroke();
+        // An empty stroke adds nothing
+        sut.commit_stroke();
+
+        assert_eq!(undo_labels(&sut), vec![expected]);
+        let Some(EditCommand::Tiles { edits, .. }) = sut.undo_entries().next() else {
+            panic!("not a tile command");
+        };
+        assert_eq!(edits.len(), count);
+    }
+
+    #[rstest]
+    #[case(3, 2, vec!["0", "1"])]
+    #[case(3, 5, vec!["2", "3", "4"])]
+    #[case(1, 2, vec!["1"])]
+    fn test_max_depth_drops_the_oldest_commands(
+        #[case] max_depth: usize,
+        #[case] pushed: usize,
+        #[case] expected: Vec<&str>,
+    ) {
+        let mut sut = EditHistory::default();
+        sut.set_max_depth(max_depth);
+
+        for i in 0..pushed {
+            sut.push(command(&i.to_string()));
+        }
+
+        assert_eq!(undo_labels(&sut), expected);
+    }
+
+    #[test]
+    fn test_lowering_max_depth_trims_the_history() {
+        let mut sut = EditHistory::default();
+        for i in 0..5 {
+            sut.push(command(&i.to_string()));
+        }
+
+        sut.set_max_depth(2);
+        assert_eq!(undo_labels(&sut), vec!["3", "4"]);
+
+        // At least one step is kept
+        sut.set_max_depth(0);
+        assert_eq!(sut.max_depth(), 1);
+        assert_eq!(undo_labels(&sut), vec!["4"]);
+    }
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn test_new_command_clears_redo(#[case] by_stroke: bool) {
+        let mut sut = EditHistory::default();
+        sut.push(command("a"));
+        sut.push(command("b"));
+        let undone = sut.undo_stack.pop().unwrap();
+        sut.redo_stack.push(undone);
+        assert!(sut.can_redo());
+
+        if by_stroke {
+            sut.record(paint(1));
+            sut.commit_stroke();
+        } else {
+            sut.push(command("c"));
+        }
+
+        assert!(!sut.can_redo());
+        assert_eq!(sut.undo_entries().count(), 2);
+    }
+
+    #[test]
+    fn test_stroke_label_is_used_once() {
+        let mut sut = EditHistory::default();
+
+        sut.label_stroke("Fill");
+        sut.record(paint(0));
+        sut.commit_stroke();
+        sut.record(paint(1));
+        sut.commit_stroke();
+
+        assert_eq!(undo_labels(&sut), vec!["Fill", "Paint"]);
+    }
+
+    #[test]
+    fn test_clear_forgets_the_stroke_label() {
+        let mut sut = EditHistory::default();
+        sut.label_stroke("Line");
+        sut.record(paint(0));
+
+        sut.clear();
+        sut.record(place(1));
+        sut.commit_stroke();
+
+        assert_eq!(undo_labels(&sut), vec!["Place Building"]);
+    }
+}
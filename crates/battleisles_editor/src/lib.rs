@@ -1,19 +1,51 @@
+use battleisles_bevy::camera_controller::CameraControllerPlugin;
+use battleisles_bevy::map_model::MapModel;
 use battleisles_bevy::map_model_plugin::MapModelPlugin;
-use battleisles_domain::map::Map;
+use battleisles_domain::map::{HexOrientation, Map, MapShape};
+use battleisles_domain::map_generator::{add_islands, IslandSettings};
 use bevy::prelude::*;
-use bevy::window::{WindowMode, WindowResized};
+use bevy::window::WindowMode;
 use bevy_egui::EguiPlugin;
+use document::EditorDocument;
+use history::{EditCommand, EditHistory};
+use resize::ResizeSettings;
+use std::path::PathBuf;
 
+mod document;
+mod history;
+mod resize;
+mod tools;
 mod ui;
 
 #[derive(Event)]
 pub struct GenerateMapEvent {
-    pub width: u32,
-    pub height: u32,
+    pub shape: MapShape,
+    pub orientation: HexOrientation,
+    // None for open sea
+    pub islands: Option<IslandSettings>,
 }
 
+// Resizes and shifts the current map, keeping its content
 #[derive(Event)]
-pub struct MapChangedEvent;
+pub struct ResizeMapEvent {
+    pub settings: ResizeSettings,
+}
+
+#[derive(Event)]
+pub struct OpenMapEvent {
+    pub path: PathBuf,
+}
+
+#[derive(Event)]
+pub struct SaveMapEvent {
+    pub path: PathBuf,
+}
+
+#[derive(Event)]
+pub struct UndoEvent;
+
+#[derive(Event)]
+pub struct RedoEvent;
 
 pub struct BattleIslesEditor;
 
@@ -21,31 +53,51 @@ impl BattleIslesEditor {
     pub fn run() {
         App::new()
             .init_resource::<ui::UiState>()
+            .insert_resource(match document::recent_files_path() {
+                Some(path) => EditorDocument::with_recent_files_at(path),
+                None => EditorDocument::default(),
+            })
+            .init_resource::<EditHistory>()
             .add_event::<GenerateMapEvent>()
-            .add_event::<MapChangedEvent>()
+            .add_event::<ResizeMapEvent>()
+            .add_event::<OpenMapEvent>()
+            .add_event::<SaveMapEvent>()
+            .add_event::<UndoEvent>()
+            .add_event::<RedoEvent>()
             .add_plugins(DefaultPlugins.set(WindowPlugin {
                 primary_window: Some(Window {
                     mode: WindowMode::Windowed,
-                    title: "Battle Isles".to_owned(),
+                    title: "Battle Isles Editor".to_owned(),
                     resolution: (800.0, 600.0).into(),
                     resizable: true,
                     canvas: Some("#bevy".to_owned()),
                     ..default()
                 }),
+                // Closing is confirmed by ui::close_requested_system when there are unsaved changes
+                close_when_requested: false,
                 ..default()
             }))
             .add_plugins(EguiPlugin {
                 enable_multipass_for_primary_context: false,
             })
-            .add_plugins(MapModelPlugin)
+            .add_plugins((MapModelPlugin, CameraControllerPlugin))
             .add_systems(Startup, setup)
             .add_systems(
                 Update,
                 (
                     ui::ui_system,
-                    ui::paint_click_system,
+                    ui::paint_system,
+                    ui::resize_preview_system,
+                    ui::close_requested_system,
+                    document::track_edits_system,
+                    document::window_title_system,
+                    history::record_tile_changes_system,
+                    history::undo_redo_shortcut_system,
+                    history::handle_undo_redo_events,
                     handle_generate_map_event,
-                    handle_map_changed_event,
+                    handle_resize_map_event,
+                    handle_open_map_event,
+                    handle_save_map_event,
                 ),
             )
             .run();
@@ -57,47 +109,160 @@ pub fn setup(mut commands: Commands) {
     commands.insert_resource(ui::UiState::default());
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_generate_map_event(
     mut events: EventReader<GenerateMapEvent>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    mut map_changed_events: EventWriter<MapChangedEvent>,
+    mut document: ResMut<EditorDocument>,
+    mut history: ResMut<EditHistory>,
+    mut ui_state: ResMut<ui::UiState>,
+    map_model: Option<Res<MapModel>>,
 ) {
     for event in events.read() {
-        println!(
-            "Generating map with dimensions: {}x{}",
-            event.width, event.height
-        );
+        println!("Generating map with shape: {:?}", event.shape);
 
-        let map = Map::new(event.width, event.height);
+        let map = Map::with_shape(event.shape, event.orientation);
+        let (map, label) = match &event.islands {
+            Some(settings) => {
+                ui_state.status = format!("Generated islands with seed {}", settings.seed);
+                (add_islands(map, settings), "Generate Islands")
+            }
+            None => (map, "Generate"),
+        };
+        let (width, height) = (map.width(), map.height());
+        let previous = map_model.as_ref().map(|map_model| map_model.map().clone());
 
-        match MapModelPlugin::initialize_map_model(map, &mut commands, &mut meshes, &mut materials)
-        {
+        match MapModelPlugin::initialize_map_model(
+            map.clone(),
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+        ) {
             Ok(_) => {
                 println!("Map generated successfully");
-                // Send MapChangedEvent to trigger camera update
-                map_changed_events.write(MapChangedEvent);
+                document.reset();
+                // Generating over an existing map can be undone; the very first map cannot
+                match previous {
+                    Some(before) => history.push(EditCommand::ReplaceMap {
+                        label: format!("{} {}x{}", label, width, height),
+                        before: Box::new(before),
+                        after: Box::new(map),
+                    }),
+                    None => history.clear(),
+                }
             }
             Err(e) => println!("Failed to generate map: {:?}", e),
         }
     }
 }
 
-fn handle_map_changed_event(
-    mut events: EventReader<MapChangedEvent>,
-    mut window_events: EventWriter<WindowResized>,
-    window_query: Query<(Entity, &Window)>,
+#[allow(clippy::too_many_arguments)]
+fn handle_resize_map_event(
+    mut events: EventReader<ResizeMapEvent>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut document: ResMut<EditorDocument>,
+    mut history: ResMut<EditHistory>,
+    mut ui_state: ResMut<ui::UiState>,
+    map_model: Option<Res<MapModel>>,
 ) {
-    for _event in events.read() {
-        println!("Map changed, triggering camera update");
-        // Send a fake WindowResized event to trigger the camera update
-        if let Ok((entity, window)) = window_query.single() {
-            window_events.write(WindowResized {
-                window: entity,
-                width: window.resolution.physical_width() as f32,
-                height: window.resolution.physical_height() as f32,
+    // The map model is only replaced once commands are applied, so a second resize in the
+    // same frame would start from the old map
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    let Some(map_model) = map_model else {
+        ui_state.status = "There is no map to resize".to_owned();
+        return;
+    };
+    let settings = &event.settings;
+    let before = map_model.map().clone();
+    let (map, dropped) = settings.apply(&before);
+
+    match MapModelPlugin::initialize_map_model(
+        map.clone(),
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+    ) {
+        Ok(_) => {
+            ui_state.status = format!(
+                "Resized to {}x{}, {} tiles dropped",
+                settings.width,
+                settings.height,
+                dropped.len()
+            );
+            document.dirty = true;
+            history.push(EditCommand::ReplaceMap {
+                label: format!("Resize {}x{}", settings.width, settings.height),
+                before: Box::new(before),
+                after: Box::new(map),
             });
         }
+        Err(e) => println!("Failed to resize map: {:?}", e),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_open_map_event(
+    mut events: EventReader<OpenMapEvent>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut document: ResMut<EditorDocument>,
+    mut history: ResMut<EditHistory>,
+    mut ui_state: ResMut<ui::UiState>,
+) {
+    for event in events.read() {
+        println!("Opening map: {}", event.path.display());
+
+        let map = match Map::load(&event.path) {
+            Ok(map) => map,
+            Err(e) => {
+                println!("Failed to open map: {}", e);
+                ui_state.status = format!("Failed to open {}: {}", event.path.display(), e);
+                document.forget_recent(&event.path);
+                continue;
+            }
+        };
+
+        match MapModelPlugin::initialize_map_model(map, &mut commands, &mut meshes, &mut materials)
+        {
+            Ok(_) => {
+                ui_state.status = format!("Opened {}", event.path.display());
+                document.set_saved_path(event.path.clone());
+                history.clear();
+            }
+            Err(e) => println!("Failed to open map: {:?}", e),
+        }
+    }
+}
+
+fn handle_save_map_event(
+    mut events: EventReader<SaveMapEvent>,
+    map_model: Option<Res<MapModel>>,
+    mut document: ResMut<EditorDocument>,
+    mut ui_state: ResMut<ui::UiState>,
+) {
+    for event in events.read() {
+        let Some(map_model) = map_model.as_ref() else {
+            ui_state.status = "There is no map to save".to_owned();
+            continue;
+        };
+
+        match map_model.map().save(&event.path) {
+            Ok(()) => {
+                println!("Saved map: {}", event.path.display());
+                ui_state.status = format!("Saved {}", event.path.display());
+                document.set_saved_path(event.path.clone());
+            }
+            Err(e) => {
+                println!("Failed to save map: {}", e);
+                ui_state.status = format!("Failed to save {}: {}", event.path.display(), e);
+            }
+        }
     }
 }
@@ -0,0 +1,200 @@
+use battleisles_domain::map::{Hex, Map, Terrain};
+use battleisles_domain::map_resize::Anchor;
+
+// Largest width or height the resize dialog offers
+pub const MAX_MAP_SIZE: u32 = 200;
+
+// What Map > Resize does to the current map: resize it, then shift the content
+#[derive(Clone, PartialEq, Debug)]
+pub struct ResizeSettings {
+    pub width: u32,
+    pub height: u32,
+    pub anchor: Anchor,
+    // Columns and rows the content moves by after resizing
+    pub shift_cols: i32,
+    pub shift_rows: i32,
+    // Terrain of the tiles that are added or left empty
+    pub fill: Terrain,
+}
+
+impl ResizeSettings {
+    // Starts out at the current size, so the map only changes as far as the user asks
+    pub fn for_map(map: &Map) -> Self {
+        let (width, height) = (map.width(), map.height());
+        ResizeSettings {
+            width,
+            height,
+            anchor: Anchor::TopLeft,
+            shift_cols: 0,
+            shift_rows: 0,
+            fill: Terrain::DeepWater,
+        }
+    }
+
+    // The resized map and where the tiles that did not fit into it were in `map`
+    pub fn apply(&self, map: &Map) -> (Map, Vec<Hex>) {
+        let mut resized = map.clone();
+        // In one go, so the shift can bring back tiles the resize alone would drop
+        let dropped = resized
+            .resize_and_move(self.width, self.height, self.moved(map), self.fill)
+            .iter()
+            .map(|tile| tile.position())
+            .collect();
+        (resized, dropped)
+    }
+
+    // Where the tiles of the resized map are in `map`, to show its bounds before applying
+    pub fn new_positions(&self, map: &Map) -> Vec<Hex> {
+        let moved = self.moved(map);
+        let shape = map.resized_shape(self.width, self.height);
+        Map::with_shape(shape, map.orientation())
+            .tiles()
+            .iter()
+            .map(|tile| tile.position() - moved)
+            .collect()
+    }
+
+    // How far the tiles of `map` move: to the anchor, then by the shift
+    fn moved(&self, map: &Map) -> Hex {
+        map.resize_offset(self.width, self.height, self.anchor) + self.shift(map)
+    }
+
+    // The shift in axial coordinates, which depend on the orientation
+    fn shift(&self, map: &Map) -> Hex {
+        map.offset_to_hex(self.shift_cols, self.shift_rows) - map.offset_to_hex(0, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use battleisles_domain::map::{HexOrientation, MapShape};
+    use rstest::rstest;
+    use std::collections::HashSet;
+
+    const FILL: Terrain = Terrain::Mountains;
+
+    // Neighbouring tiles differ, and none has the fill terrain
+    fn patterned_map(orientation: HexOrientation) -> Map {
+        let shape = MapShape::TrimmedRectangle {
+            width: 5,
+            height: 5,
+        };
+        let mut map = Map::with_shape(shape, orientation);
+        let terrains = [
+            Terrain::Plains,
+            Terrain::Hills,
+            Terrain::DeepWater,
+            Terrain::ShallowWater,
+        ];
+        for (i, tile) in map.tiles_mut().iter_mut().enumerate() {
+            tile.terrain = terrains[i % terrains.len()];
+        }
+        map
+    }
+
+    #[rstest]
+    #[case(HexOrientation::Pointy, 0, 0)]
+    #[case(HexOrientation::Pointy, 0, 1)]
+    #[case(HexOrientation::Pointy, 2, -3)]
+    #[case(HexOrientation::Flat, 0, 1)]
+    #[case(HexOrientation::Flat, 1, 1)]
+    #[case(HexOrientation::Flat, -3, 2)]
+    fn test_preview_matches_the_resized_map(
+        #[case] orientation: HexOrientation,
+        #[case] shift_cols: i32,
+        #[case] shift_rows: i32,
+    ) {
+        let map = patterned_map(orientation);
+        let original = map
+            .tiles()
+            .iter()
+            .map(|tile| tile.position())
+            .collect::<HashSet<_>>();
+        for (width, height) in [(7, 6), (3, 4), (5, 5)] {
+            for anchor in Anchor::ALL {
+                let sut = ResizeSettings {
+                    width,
+                    height,
+                    anchor,
+                    shift_cols,
+                    shift_rows,
+                    fill: FILL,
+                };
+                let context = format!("{}x{} at {:?}", width, height, anchor);
+
+                let (resized, dropped) = sut.apply(&map);
+                let new_positions = sut.new_positions(&map);
+
+                let dropped_set = dropped.iter().copied().collect::<HashSet<_>>();
+                assert_eq!(dropped_set.len(), dropped.len(), "{}", context);
+                assert!(dropped_set.is_subset(&original), "{}", context);
+                assert_eq!(new_positions.len(), resized.tiles().len(), "{}", context);
+                // Tile by tile, each new tile shows the old tile under it or the fill
+                for (tile, old_position) in resized.tiles().iter().zip(&new_positions) {
+                    let expected = match map.tile_at(*old_position) {
+                        Some(old) => old.terrain,
+                        None => FILL,
+                    };
+                    assert_eq!(tile.terrain, expected, "{} {:?}", context, old_position);
+                }
+                // Whatever the preview does not cover is reported as dropped
+                let kept = new_positions.iter().copied().collect::<HashSet<_>>();
+                assert_eq!(
+                    original.difference(&kept).copied().collect::<HashSet<_>>(),
+                    dropped_set,
+                    "{}",
+                    context
+                );
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(HexOrientation::Pointy, 1, 1)]
+    #[case(HexOrientation::Pointy, -2, 3)]
+    #[case(HexOrientation::Flat, 1, 1)]
+    #[case(HexOrientation::Flat, 3, -2)]
+    fn test_shift_moves_the_top_left_tile_by_columns_and_rows(
+        #[case] orientation: HexOrientation,
+        #[case] shift_cols: i32,
+        #[case] shift_rows: i32,
+    ) {
+        let map = patterned_map(orientation);
+        let sut = ResizeSettings {
+            shift_cols,
+            shift_rows,
+            ..ResizeSettings::for_map(&map)
+        };
+
+        let shift = sut.shift(&map);
+
+        // Other lines may move a tile further, hex rows (columns) are offset by half a tile
+        let top_left = map.offset_to_hex(0, 0);
+        assert_eq!(
+            map.hex_to_offset(top_left + shift),
+            (shift_cols, shift_rows)
+        );
+    }
+
+    #[test]
+    fn test_settings_start_at_the_current_size() {
+        let map = Map::new(6, 4);
+        let sut = ResizeSettings::for_map(&map);
+
+        let (resized, dropped) = sut.apply(&map);
+
+        assert!(dropped.is_empty());
+        assert_eq!(
+            resized
+                .tiles()
+                .iter()
+                .map(|tile| tile.position())
+                .collect::<Vec<_>>(),
+            map.tiles()
+                .iter()
+                .map(|tile| tile.position())
+                .collect::<Vec<_>>()
+        );
+    }
+}
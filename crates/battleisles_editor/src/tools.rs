@@ -0,0 +1,69 @@
+use battleisles_domain::map::{Hex, Map, Tile};
+use std::collections::HashSet;
+
+// Largest brush; size 1 is a single tile and every further size adds a ring around it
+pub const MAX_BRUSH_SIZE: u32 = 5;
+
+// How terrain is painted, picked next to the terrain palette
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PaintTool {
+    // Paints under the cursor while the button is held
+    Brush,
+    // Replaces the connected area of the clicked tile's terrain
+    Fill,
+    // Paint from where the button is pressed to where it is released
+    Line,
+    Rectangle,
+}
+
+impl PaintTool {
+    pub const ALL: [PaintTool; 4] = [
+        PaintTool::Brush,
+        PaintTool::Fill,
+        PaintTool::Line,
+        PaintTool::Rectangle,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PaintTool::Brush => "Brush",
+            PaintTool::Fill => "Fill",
+            PaintTool::Line => "Line",
+            PaintTool::Rectangle => "Rectangle",
+        }
+    }
+
+    // Label of the edit in the history
+    pub fn history_label(self) -> &'static str {
+        match self {
+            PaintTool::Brush => "Paint",
+            PaintTool::Fill => "Fill",
+            PaintTool::Line => "Line",
+            PaintTool::Rectangle => "Rectangle",
+        }
+    }
+
+    pub fn uses_brush_size(self) -> bool {
+        matches!(self, PaintTool::Brush | PaintTool::Line)
+    }
+}
+
+// Tiles `tool` paints when dragged from `start` to `end`, each listed once
+pub fn tool_tiles(tool: PaintTool, map: &Map, start: Hex, end: Hex, brush_size: u32) -> Vec<Hex> {
+    let radius = brush_size.clamp(1, MAX_BRUSH_SIZE) - 1;
+    let tiles: Vec<&Tile> = match tool {
+        PaintTool::Brush => map.range(end, radius).collect(),
+        PaintTool::Fill => map.region(end),
+        PaintTool::Line => map
+            .line(start, end)
+            .flat_map(|tile| map.range(tile.position(), radius))
+            .collect(),
+        PaintTool::Rectangle => map.rectangle(start, end).collect(),
+    };
+    let mut seen = HashSet::new();
+    tiles
+        .into_iter()
+        .map(Tile::position)
+        .filter(|hex| seen.insert(*hex))
+        .collect()
+}
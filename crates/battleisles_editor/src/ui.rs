@@ -1,62 +1,402 @@
-use crate::GenerateMapEvent;
-use battleisles_bevy::map_model_plugin::ApplyTerrainAt;
-use battleisles_domain::map::Terrain;
+use crate::document::EditorDocument;
+use crate::history::EditHistory;
+use crate::resize::{ResizeSettings, MAX_MAP_SIZE};
+use crate::tools::{tool_tiles, PaintTool, MAX_BRUSH_SIZE};
+use crate::{GenerateMapEvent, OpenMapEvent, RedoEvent, ResizeMapEvent, SaveMapEvent, UndoEvent};
+use battleisles_bevy::building_materials::owner_color;
+use battleisles_bevy::camera_controller::FitMapToView;
+use battleisles_bevy::map_model::MapModel;
+use battleisles_bevy::map_model_plugin::{ApplyBuildingAt, ApplyTerrainAt, TerrainSymmetry};
+use battleisles_bevy::terrain_theme::{CurrentTerrainTheme, TerrainTheme};
+use battleisles_domain::building::{Building, BuildingKind};
+use battleisles_domain::map::{Hex, HexOrientation, MapShape, Symmetry, Terrain};
+use battleisles_domain::map_generator::IslandSettings;
+use battleisles_domain::map_resize::Anchor;
+use battleisles_domain::player::PlayerId;
 use bevy::prelude::*;
 use bevy::input::ButtonInput;
+use bevy::window::WindowCloseRequested;
 use bevy_egui::{egui, EguiContexts};
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_6;
+use std::hash::{BuildHasher, RandomState};
+use std::path::PathBuf;
 
 #[derive(Resource)]
 pub struct UiState {
     pub map_width: String,
     pub map_height: String,
+    // Outline and hex orientation of new maps
+    pub shape: ShapeKind,
+    pub flat_top: bool,
+    // Used by "Generate Islands"
+    pub islands: IslandSettings,
     pub selected_terrain: Terrain,
+    pub brush: Brush,
+    // How terrain is painted, and how many tiles across the brush and lines are
+    pub tool: PaintTool,
+    pub brush_size: u32,
+    // Owner given to newly placed buildings, None for neutral
+    pub building_owner: Option<PlayerId>,
+    pub status: String,
+    pub path_input: String,
+    pub file_dialog: Option<FileDialog>,
+    pub pending_action: Option<FileAction>,
+    pub resize_dialog: Option<ResizeDialog>,
 }
 
-// Send paint events when user clicks in the main viewport (not over egui)
-pub fn paint_click_system(
+// Outlines offered for new maps, see MapShape
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ShapeKind {
+    TrimmedRectangle,
+    Rectangle,
+    Hexagon,
+    Parallelogram,
+}
+
+impl ShapeKind {
+    const ALL: [ShapeKind; 4] = [
+        ShapeKind::TrimmedRectangle,
+        ShapeKind::Rectangle,
+        ShapeKind::Hexagon,
+        ShapeKind::Parallelogram,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            ShapeKind::TrimmedRectangle => "Trimmed Rectangle",
+            ShapeKind::Rectangle => "Rectangle",
+            ShapeKind::Hexagon => "Hexagon",
+            ShapeKind::Parallelogram => "Parallelogram",
+        }
+    }
+
+    // Hexagons fit the smaller of width and height
+    fn map_shape(self, width: u32, height: u32) -> MapShape {
+        match self {
+            ShapeKind::TrimmedRectangle => MapShape::TrimmedRectangle { width, height },
+            ShapeKind::Rectangle => MapShape::Rectangle { width, height },
+            ShapeKind::Hexagon => MapShape::Hexagon {
+                radius: width.min(height).saturating_sub(1) / 2,
+            },
+            ShapeKind::Parallelogram => MapShape::Parallelogram { width, height },
+        }
+    }
+}
+
+// What a click in the viewport applies to the tile under the cursor
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Brush {
+    Terrain,
+    // None removes the building
+    Building(Option<BuildingKind>),
+}
+
+// Owners offered by the buildings palette besides neutral
+const MAX_BUILDING_OWNERS: u8 = 4;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FileDialog {
+    Open,
+    SaveAs,
+}
+
+pub struct ResizeDialog {
+    pub settings: ResizeSettings,
+    // Where the tiles the settings would drop are, kept up to date with the settings and map
+    pub dropped: Vec<Hex>,
+}
+
+// Actions that throw away the current map and therefore need confirmation when it is dirty
+#[derive(Clone, PartialEq, Debug)]
+pub enum FileAction {
+    New,
+    NewIslands,
+    Open,
+    OpenRecent(PathBuf),
+    Quit,
+}
+
+// Window close requests are not handled by bevy (see close_when_requested) so that
+// unsaved changes can be confirmed first
+pub fn close_requested_system(
+    mut close_requests: EventReader<WindowCloseRequested>,
+    document: Res<EditorDocument>,
+    mut ui_state: ResMut<UiState>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if close_requests.read().count() == 0 {
+        return;
+    }
+    if document.dirty {
+        ui_state.pending_action = Some(FileAction::Quit);
+    } else {
+        exit.write(AppExit::Success);
+    }
+}
+
+// A drag that started in the viewport, which paints even if it started off the map
+#[derive(Default)]
+pub struct PaintDrag {
+    active: bool,
+    // First tile the drag was over
+    start: Option<Hex>,
+    // Tile the drag was over last
+    last: Option<Hex>,
+}
+
+// Send paint events for the tiles the selected tool covers while the user clicks or drags in
+// the main viewport (not over egui), and outline the tiles a click or the drag would paint
+#[allow(clippy::too_many_arguments)]
+pub fn paint_system(
     mut contexts: EguiContexts,
     mouse: Res<ButtonInput<MouseButton>>,
     windows: Query<&Window>,
     q_camera: Query<(&Camera, &GlobalTransform)>,
     ui_state: Res<UiState>,
+    map_model: Option<Res<MapModel>>,
+    symmetry: Res<TerrainSymmetry>,
+    mut history: ResMut<EditHistory>,
     mut paint_events: EventWriter<ApplyTerrainAt>,
+    mut building_events: EventWriter<ApplyBuildingAt>,
+    mut gizmos: Gizmos,
+    mut drag: Local<PaintDrag>,
 ) {
-    let ctx = contexts.ctx_mut();
-    if ctx.wants_pointer_input() {
-        return;
+    let Some(map_model) = map_model else { return; };
+    let over_ui = contexts.ctx_mut().wants_pointer_input();
+    let hovered = (|| {
+        let cursor_pos = windows.single().ok()?.cursor_position()?;
+        let (camera, camera_transform) = q_camera.single().ok()?;
+        let ray = camera.viewport_to_world(camera_transform, cursor_pos).ok()?;
+        map_model.hex_at(map_model.pick(ray)?)
+    })();
+
+    if mouse.just_pressed(MouseButton::Left) && !over_ui {
+        *drag = PaintDrag {
+            active: true,
+            ..default()
+        };
     }
-    if !mouse.just_pressed(MouseButton::Left) {
-        return;
+    if drag.active && drag.start.is_none() {
+        drag.start = hovered;
     }
+    let clicked = drag.active && mouse.just_pressed(MouseButton::Left);
+    let released = drag.active && mouse.just_released(MouseButton::Left);
+    let map = map_model.map();
+    let size = ui_state.brush_size;
+    let mut paint = Vec::new();
+    let mut preview = Vec::new();
 
-    let window = match windows.single() {
-        Ok(w) => w,
-        Err(_) => return,
-    };
-    let (camera, camera_transform) = match q_camera.single() {
-        Ok(v) => v,
-        Err(_) => return,
-    };
-    let Some(cursor_pos) = window.cursor_position() else { return; };
-    if let Ok(world) = camera.viewport_to_world_2d(camera_transform, cursor_pos) {
-        paint_events.write(ApplyTerrainAt {
-            world_pos: world,
-            terrain: ui_state.selected_terrain,
+    match ui_state.brush {
+        Brush::Building(kind) => {
+            let target = hovered.filter(|_| clicked);
+            if let Some(world_pos) = target.and_then(|hex| map_model.tile_world_pos(hex)) {
+                history.label_stroke("Place Building");
+                building_events.write(ApplyBuildingAt {
+                    world_pos,
+                    building: kind.map(|kind| Building::new(kind, ui_state.building_owner)),
+                });
+            }
+            preview.extend(hovered);
+        }
+        Brush::Terrain => match (ui_state.tool, drag.start, hovered) {
+            (PaintTool::Brush, _, Some(hex)) => {
+                if drag.active && drag.last != Some(hex) {
+                    // Along the way from the last tile, so fast drags leave no gaps
+                    let from = drag.last.unwrap_or(hex);
+                    paint = tool_tiles(PaintTool::Line, map, from, hex, size);
+                }
+                preview = tool_tiles(PaintTool::Brush, map, hex, hex, size);
+            }
+            (PaintTool::Fill, _, Some(hex)) => {
+                if clicked {
+                    paint = tool_tiles(PaintTool::Fill, map, hex, hex, size);
+                } else if !drag.active {
+                    preview = tool_tiles(PaintTool::Fill, map, hex, hex, size);
+                }
+            }
+            (tool @ (PaintTool::Line | PaintTool::Rectangle), Some(start), _) if drag.active => {
+                let end = hovered.or(drag.last).unwrap_or(start);
+                if released {
+                    paint = tool_tiles(tool, map, start, end, size);
+                } else {
+                    preview = tool_tiles(tool, map, start, end, size);
+                }
+            }
+            (PaintTool::Line, _, Some(hex)) => {
+                preview = tool_tiles(PaintTool::Brush, map, hex, hex, size);
+            }
+            (PaintTool::Rectangle, _, Some(hex)) => preview.push(hex),
+            _ => {}
+        },
+    }
+
+    if !paint.is_empty() {
+        history.label_stroke(ui_state.tool.history_label());
+        let terrain = ui_state.selected_terrain;
+        for world_pos in paint.into_iter().filter_map(|hex| map_model.tile_world_pos(hex)) {
+            paint_events.write(ApplyTerrainAt { world_pos, terrain });
+        }
+    }
+    if drag.active || !over_ui {
+        // Terrain edits are mirrored by the map model, see TerrainSymmetry
+        let mirrored = match (ui_state.brush, symmetry.0) {
+            (Brush::Terrain, Some(symmetry)) => preview
+                .iter()
+                .filter_map(|hex| map.mirror_hex(*hex, symmetry))
+                .collect(),
+            _ => Vec::new(),
+        };
+        for hex in preview.into_iter().chain(mirrored) {
+            outline_tile(&mut gizmos, &map_model, hex, Color::WHITE);
+        }
+    }
+    if hovered.is_some() {
+        drag.last = hovered;
+    }
+    if !mouse.pressed(MouseButton::Left) {
+        *drag = PaintDrag::default();
+    }
+}
+
+// Outline the bounds the map gets from the resize dialog's settings and the tiles that would
+// be dropped
+pub fn resize_preview_system(
+    ui_state: Res<UiState>,
+    map_model: Option<Res<MapModel>>,
+    mut gizmos: Gizmos,
+) {
+    let (Some(dialog), Some(map_model)) = (&ui_state.resize_dialog, map_model) else { return; };
+    let bounds = dialog
+        .settings
+        .new_positions(map_model.map())
+        .into_iter()
+        .map(|hex| map_model.hex_world_pos(hex))
+        .fold(None, |bounds: Option<Rect>, center| {
+            Some(bounds.map_or(Rect::from_center_size(center, Vec2::ZERO), |bounds| bounds.union_point(center)))
         });
+    if let Some(bounds) = bounds {
+        let bounds = bounds.inflate(map_model.map().hex_size());
+        let top = Terrain::ALL
+            .map(|terrain| map_model.tile_top(terrain))
+            .into_iter()
+            .fold(0.0, f32::max);
+        let isometry = Isometry3d::from_translation(bounds.center().extend(top));
+        gizmos.rect(isometry, bounds.size(), Color::srgb(1.0, 0.8, 0.0));
+    }
+    for hex in &dialog.dropped {
+        outline_tile(&mut gizmos, &map_model, *hex, Color::srgb(1.0, 0.2, 0.2));
+    }
+}
+
+// Hexagon just above the top of the tile
+fn outline_tile(gizmos: &mut Gizmos, map_model: &MapModel, hex: Hex, color: Color) {
+    let Some(surface) = map_model.tile_surface(hex) else { return; };
+    let hex_size = map_model.map().hex_size();
+    // Six segments starting at a corner on the X axis like flat-top tiles, turned for pointy-top ones
+    let angle = first_corner_angle(map_model.map().orientation());
+    let isometry = Isometry3d::new(surface + Vec3::Z * 0.01 * hex_size, Quat::from_rotation_z(angle));
+    gizmos
+        .circle(isometry, hex_size * 0.9, color)
+        .resolution(6);
+}
+
+// Angle of the first corner of a hex from the X axis
+fn first_corner_angle(orientation: HexOrientation) -> f32 {
+    match orientation {
+        HexOrientation::Pointy => FRAC_PI_6,
+        HexOrientation::Flat => 0.0,
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn ui_system(
     mut contexts: EguiContexts,
     mut ui_state: ResMut<UiState>,
+    document: Res<EditorDocument>,
+    mut history: ResMut<EditHistory>,
     mut map_events: EventWriter<GenerateMapEvent>,
+    mut open_events: EventWriter<OpenMapEvent>,
+    mut save_events: EventWriter<SaveMapEvent>,
+    mut undo_events: EventWriter<UndoEvent>,
+    mut redo_events: EventWriter<RedoEvent>,
+    mut exit: EventWriter<AppExit>,
+    mut fit_events: EventWriter<FitMapToView>,
+    terrain_theme: Res<CurrentTerrainTheme>,
+    mut symmetry: ResMut<TerrainSymmetry>,
+    map_model: Option<Res<MapModel>>,
+    mut resize_events: EventWriter<ResizeMapEvent>,
 ) {
+    let icons = Terrain::ALL
+        .into_iter()
+        .filter_map(|terrain| Some((terrain, contexts.add_image(terrain_theme.icon(terrain)?))))
+        .collect::<HashMap<_, _>>();
     let ctx = contexts.ctx_mut();
+    let mut requested_action = None;
 
     // Top panel
     egui::TopBottomPanel::top("top_panel")
         .default_height(50.0)
         .show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    if ui.button("New").clicked() {
+                        requested_action = Some(FileAction::New);
+                        ui.close_menu();
+                    }
+                    if ui.button("Open...").clicked() {
+                        requested_action = Some(FileAction::Open);
+                        ui.close_menu();
+                    }
+                    ui.menu_button("Recent Files", |ui| {
+                        if document.recent_files.is_empty() {
+                            ui.label("No recent files");
+                        }
+                        for path in &document.recent_files {
+                            if ui.button(path.display().to_string()).clicked() {
+                                requested_action = Some(FileAction::OpenRecent(path.clone()));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    ui.separator();
+                    if ui.button("Save").clicked() {
+                        match &document.path {
+                            Some(path) => {
+                                save_events.write(SaveMapEvent { path: path.clone() });
+                            }
+                            None => open_file_dialog(&mut ui_state, &document, FileDialog::SaveAs),
+                        }
+                        ui.close_menu();
+                    }
+                    if ui.button("Save As...").clicked() {
+                        open_file_dialog(&mut ui_state, &document, FileDialog::SaveAs);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("Exit").clicked() {
+                        requested_action = Some(FileAction::Quit);
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("Map", |ui| {
+                    let resize = ui.add_enabled(map_model.is_some(), egui::Button::new("Resize / Shift..."));
+                    if let (true, Some(map_model)) = (resize.clicked(), &map_model) {
+                        let settings = ResizeSettings::for_map(map_model.map());
+                        ui_state.resize_dialog = Some(ResizeDialog {
+                            settings,
+                            dropped: Vec::new(),
+                        });
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("View", |ui| {
+                    if ui.button("Fit Map to View (Home)").clicked() {
+                        fit_events.write(FitMapToView);
+                        ui.close_menu();
+                    }
+                });
+            });
             ui.horizontal(|ui| {
                 ui.label("Map Width:");
                 ui.add(
@@ -70,40 +410,285 @@ pub fn ui_system(
                         .hint_text("Height")
                         .desired_width(60.0),
                 );
+                ui.label("Shape:");
+                egui::ComboBox::from_id_salt("map_shape")
+                    .selected_text(ui_state.shape.name())
+                    .show_ui(ui, |ui| {
+                        for shape in ShapeKind::ALL {
+                            ui.selectable_value(&mut ui_state.shape, shape, shape.name());
+                        }
+                    });
+                ui.checkbox(&mut ui_state.flat_top, "Flat-top");
                 if ui.add(egui::Button::new("Generate Map")).clicked() {
-                    if let (Ok(width), Ok(height)) = (
-                        ui_state.map_width.parse::<u32>(),
-                        ui_state.map_height.parse::<u32>(),
-                    ) {
-                        map_events.write(GenerateMapEvent { width, height });
-                    }
+                    requested_action = Some(FileAction::New);
+                }
+            });
+            ui.horizontal(|ui| {
+                let islands = &mut ui_state.islands;
+                ui.label("Seed:");
+                ui.add(egui::DragValue::new(&mut islands.seed));
+                if ui.button("New Seed").clicked() {
+                    islands.seed = RandomState::new().hash_one(islands.seed);
+                }
+                ui.label("Land:");
+                ui.add(
+                    egui::Slider::new(&mut islands.land_ratio, 0.05..=0.9)
+                        .custom_formatter(|ratio, _| format!("{:.0}%", ratio * 100.0))
+                        .custom_parser(|text| {
+                            let percent = text.trim_end_matches('%').parse::<f64>().ok()?;
+                            Some(percent / 100.0)
+                        }),
+                );
+                ui.label("Islands:");
+                ui.add(egui::DragValue::new(&mut islands.island_count).range(1..=12));
+                ui.label("Shallows:");
+                ui.add(egui::DragValue::new(&mut islands.shallows).range(0..=3));
+                if ui.add(egui::Button::new("Generate Islands")).clicked() {
+                    requested_action = Some(FileAction::NewIslands);
                 }
             });
         });
 
-    // Bottom panel
+    // Bottom panel: status line
     egui::TopBottomPanel::bottom("bottom_panel")
         .default_height(50.0)
         .show(ctx, |ui| {
-            ui.add(egui::Label::new("Bottom Panel"));
+            ui.add(egui::Label::new(ui_state.status.as_str()));
         });
 
-    // Left panel: terrain palette
+    // Left panel: terrain and building palettes
     egui::SidePanel::left("left_panel")
         .default_width(140.0)
         .show(ctx, |ui| {
-            ui.heading("Terrain");
-            ui.separator();
-            terrain_palette(ui, &mut ui_state.selected_terrain);
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.heading("Terrain");
+                ui.separator();
+                let active = ui_state.brush == Brush::Terrain;
+                let ui_state = &mut *ui_state;
+                if tool_palette(ui, &mut ui_state.tool, &mut ui_state.brush_size, active) {
+                    ui_state.brush = Brush::Terrain;
+                }
+                symmetry_picker(ui, &mut symmetry.0);
+                ui.add_space(8.0);
+                let theme = terrain_theme.theme();
+                // Swatches are shaped like the tiles of the current map
+                let orientation = map_model
+                    .as_ref()
+                    .map_or(HexOrientation::Pointy, |map_model| map_model.map().orientation());
+                let selected = &mut ui_state.selected_terrain;
+                if terrain_palette(ui, selected, active, orientation, theme, &icons) {
+                    ui_state.brush = Brush::Terrain;
+                }
+                ui.add_space(8.0);
+                ui.heading("Buildings");
+                ui.separator();
+                buildings_palette(ui, &mut ui_state.brush, &mut ui_state.building_owner);
+            });
         });
 
-    // Right panel
+    // Right panel: edit history
     egui::SidePanel::right("right_panel")
-        .default_width(100.0)
+        .default_width(160.0)
         .show(ctx, |ui| {
-            ui.add(egui::Label::new("Right Panel"));
+            ui.heading("History");
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.add_enabled(history.can_undo(), egui::Button::new("Undo")).clicked() {
+                    undo_events.write(UndoEvent);
+                }
+                if ui.add_enabled(history.can_redo(), egui::Button::new("Redo")).clicked() {
+                    redo_events.write(RedoEvent);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Depth:");
+                let mut max_depth = history.max_depth();
+                if ui
+                    .add(egui::DragValue::new(&mut max_depth).range(1..=1000))
+                    .changed()
+                {
+                    history.set_max_depth(max_depth);
+                }
+            });
+            ui.separator();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for command in history.undo_entries() {
+                    ui.label(command.label());
+                }
+                for command in history.redo_entries() {
+                    ui.weak(command.label());
+                }
+            });
         });
 
+    // Path prompt for Open / Save As
+    if let Some(dialog) = ui_state.file_dialog {
+        let title = match dialog {
+            FileDialog::Open => "Open Map",
+            FileDialog::SaveAs => "Save Map As",
+        };
+        let mut confirmed = false;
+        let mut cancelled = false;
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("File path:");
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut ui_state.path_input)
+                        .hint_text("maps/island.ron")
+                        .desired_width(300.0),
+                );
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    confirmed = true;
+                }
+                ui.horizontal(|ui| {
+                    confirmed |= ui.button("OK").clicked();
+                    cancelled = ui.button("Cancel").clicked();
+                });
+            });
+        let path = ui_state.path_input.trim().to_owned();
+        if confirmed && !path.is_empty() {
+            let path = PathBuf::from(path);
+            match dialog {
+                FileDialog::Open => {
+                    open_events.write(OpenMapEvent { path });
+                }
+                FileDialog::SaveAs => {
+                    save_events.write(SaveMapEvent { path });
+                }
+            }
+            ui_state.file_dialog = None;
+        } else if cancelled {
+            ui_state.file_dialog = None;
+        }
+    }
+
+    // Resize / shift settings, previewed in the viewport by resize_preview_system
+    if let (Some(dialog), Some(map_model)) = (ui_state.resize_dialog.as_mut(), &map_model) {
+        let mut settings = dialog.settings.clone();
+        let mut apply = false;
+        let mut cancelled = false;
+        egui::Window::new("Resize Map")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let map = map_model.map();
+                ui.label(format!("Current size: {}x{}", map.width(), map.height()));
+                egui::Grid::new("resize_settings").num_columns(2).show(ui, |ui| {
+                    ui.label("Width:");
+                    ui.add(egui::DragValue::new(&mut settings.width).range(1..=MAX_MAP_SIZE));
+                    ui.end_row();
+                    ui.label("Height:");
+                    ui.add(egui::DragValue::new(&mut settings.height).range(1..=MAX_MAP_SIZE));
+                    ui.end_row();
+                    ui.label("Anchor:");
+                    anchor_picker(ui, &mut settings.anchor);
+                    ui.end_row();
+                    ui.label("Shift:");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut settings.shift_cols).prefix("columns "));
+                        ui.add(egui::DragValue::new(&mut settings.shift_rows).prefix("rows "));
+                    });
+                    ui.end_row();
+                    ui.label("Fill:");
+                    egui::ComboBox::from_id_salt("resize_fill")
+                        .selected_text(terrain_name(settings.fill))
+                        .show_ui(ui, |ui| {
+                            for terrain in Terrain::ALL {
+                                ui.selectable_value(&mut settings.fill, terrain, terrain_name(terrain));
+                            }
+                        });
+                    ui.end_row();
+                });
+                ui.separator();
+                match dialog.dropped.len() {
+                    0 => ui.label("No tiles will be dropped"),
+                    n => ui.colored_label(egui::Color32::DARK_RED, format!("{} tiles will be dropped", n)),
+                };
+                ui.horizontal(|ui| {
+                    apply = ui.button("Apply").clicked();
+                    cancelled = ui.button("Cancel").clicked();
+                });
+            });
+        // Painting while the dialog is open changes the map too
+        if settings != dialog.settings || map_model.is_changed() {
+            dialog.dropped = settings.apply(map_model.map()).1;
+            dialog.settings = settings;
+        }
+        if apply {
+            resize_events.write(ResizeMapEvent {
+                settings: dialog.settings.clone(),
+            });
+            ui_state.resize_dialog = None;
+        } else if cancelled {
+            ui_state.resize_dialog = None;
+        }
+    }
+
+    // Confirmation before discarding unsaved changes
+    if let Some(action) = ui_state.pending_action.clone() {
+        let mut discard = false;
+        let mut cancelled = false;
+        egui::Window::new("Unsaved Changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} has unsaved changes. Discard them?",
+                    document.display_name()
+                ));
+                ui.horizontal(|ui| {
+                    discard = ui.button("Discard").clicked();
+                    cancelled = ui.button("Cancel").clicked();
+                });
+            });
+        if discard {
+            ui_state.pending_action = None;
+            requested_action = Some(action);
+        } else if cancelled {
+            ui_state.pending_action = None;
+        }
+    } else if let Some(action) = requested_action.take() {
+        if document.dirty {
+            ui_state.pending_action = Some(action);
+        } else {
+            requested_action = Some(action);
+        }
+    }
+
+    match requested_action {
+        Some(action @ (FileAction::New | FileAction::NewIslands)) => {
+            if let (Ok(width), Ok(height)) = (
+                ui_state.map_width.parse::<u32>(),
+                ui_state.map_height.parse::<u32>(),
+            ) {
+                let islands = (action == FileAction::NewIslands).then(|| ui_state.islands.clone());
+                let orientation = match ui_state.flat_top {
+                    true => HexOrientation::Flat,
+                    false => HexOrientation::Pointy,
+                };
+                map_events.write(GenerateMapEvent {
+                    shape: ui_state.shape.map_shape(width, height),
+                    orientation,
+                    islands,
+                });
+            } else {
+                ui_state.status = "Enter a map width and height first".to_owned();
+            }
+        }
+        Some(FileAction::Open) => open_file_dialog(&mut ui_state, &document, FileDialog::Open),
+        Some(FileAction::OpenRecent(path)) => {
+            open_events.write(OpenMapEvent { path });
+        }
+        Some(FileAction::Quit) => {
+            exit.write(AppExit::Success);
+        }
+        None => {}
+    }
+
     // Set the background color of the panels to light blue
     ctx.set_visuals(egui::Visuals {
         panel_fill: egui::Color32::from_rgb(173, 216, 230),
@@ -111,26 +696,53 @@ pub fn ui_system(
     });
 }
 
+fn open_file_dialog(ui_state: &mut UiState, document: &EditorDocument, dialog: FileDialog) {
+    ui_state.path_input = document
+        .path
+        .as_ref()
+        .map(|path| path.display().to_string())
+        .unwrap_or_default();
+    ui_state.file_dialog = Some(dialog);
+}
+
 impl Default for UiState {
     fn default() -> Self {
-        Self { map_width: String::new(), map_height: String::new(), selected_terrain: Terrain::Plains }
+        Self {
+            map_width: String::new(),
+            map_height: String::new(),
+            shape: ShapeKind::TrimmedRectangle,
+            flat_top: false,
+            islands: IslandSettings::default(),
+            selected_terrain: Terrain::Plains,
+            brush: Brush::Terrain,
+            tool: PaintTool::Brush,
+            brush_size: 1,
+            building_owner: None,
+            status: String::new(),
+            path_input: String::new(),
+            file_dialog: None,
+            pending_action: None,
+            resize_dialog: None,
+        }
     }
 }
 
-fn terrain_palette(ui: &mut egui::Ui, selected: &mut Terrain) {
-    use bevy_color::palettes::basic::*;
-    let items = [
-        (Terrain::Plains, GREEN, "Plains"),
-        (Terrain::Hills, OLIVE, "Hills"),
-        (Terrain::Mountains, GRAY, "Mountains"),
-        (Terrain::DeepWater, BLUE, "Deep Water"),
-        (Terrain::ShallowWater, AQUA, "Shallow Water"),
-    ];
-
-    for (terrain, color, label) in items {
+// Returns true if a swatch was clicked. The selection is only highlighted while `active`.
+// Swatches show the theme's icon for the terrain if it has one, its base colour otherwise.
+fn terrain_palette(
+    ui: &mut egui::Ui,
+    selected: &mut Terrain,
+    active: bool,
+    orientation: HexOrientation,
+    theme: &TerrainTheme,
+    icons: &HashMap<Terrain, egui::TextureId>,
+) -> bool {
+    let mut clicked = false;
+    for terrain in Terrain::ALL {
         let size = egui::vec2(40.0, 40.0);
         let (id, rect) = ui.allocate_space(size);
     let stroke = egui::Stroke::new(2.0, egui::Color32::BLACK);
+        let color = theme.style(terrain).base_color;
         let fill = egui::Color32::from_rgb(
             (color.red * 255.0) as u8,
             (color.green * 255.0) as u8,
@@ -140,15 +752,23 @@ fn terrain_palette(ui: &mut egui::Ui, selected: &mut Terrain) {
         let r = 18.0;
         let mut points = Vec::with_capacity(6);
         for i in 0..6 {
-            let a = (i as f32) * std::f32::consts::TAU / 6.0 + std::f32::consts::FRAC_PI_6; // pointy-top
+            let a = (i as f32) * std::f32::consts::TAU / 6.0 + first_corner_angle(orientation);
             points.push(center + egui::vec2(a.cos() * r, a.sin() * r));
         }
         let painter = ui.painter_at(rect);
-        painter.add(egui::epaint::PathShape::convex_polygon(points.clone(), fill, stroke));
-        let is_selected = *selected == terrain;
+        match icons.get(&terrain) {
+            Some(&icon) => {
+                egui::Image::new((icon, size)).paint_at(ui, rect);
+                painter.add(egui::epaint::PathShape::closed_line(points.clone(), stroke));
+            }
+            None => {
+                painter.add(egui::epaint::PathShape::convex_polygon(points.clone(), fill, stroke));
+            }
+        }
+        let is_selected = active && *selected == terrain;
         let resp = ui.interact(rect, id, egui::Sense::click());
-        if resp.clicked() { *selected = terrain; }
-        ui.label(label);
+        if resp.clicked() { *selected = terrain; clicked = true; }
+        ui.label(terrain_name(terrain));
         if is_selected {
             let sel_stroke = egui::Stroke::new(2.0, egui::Color32::YELLOW);
             painter.add(egui::epaint::PathShape::convex_polygon(
@@ -159,5 +779,113 @@ fn terrain_palette(ui: &mut egui::Ui, selected: &mut Terrain) {
         }
         ui.add_space(4.0);
     }
+    clicked
+}
+
+fn terrain_name(terrain: Terrain) -> &'static str {
+    match terrain {
+        Terrain::Plains => "Plains",
+        Terrain::Hills => "Hills",
+        Terrain::Mountains => "Mountains",
+        Terrain::DeepWater => "Deep Water",
+        Terrain::ShallowWater => "Shallow Water",
+    }
+}
+
+// Returns true if a tool was picked or the brush size changed. The selection is only
+// highlighted while `active`.
+fn tool_palette(ui: &mut egui::Ui, tool: &mut PaintTool, brush_size: &mut u32, active: bool) -> bool {
+    let mut changed = false;
+    ui.horizontal_wrapped(|ui| {
+        for candidate in PaintTool::ALL {
+            if ui.selectable_label(active && *tool == candidate, candidate.name()).clicked() {
+                *tool = candidate;
+                changed = true;
+            }
+        }
+    });
+    ui.add_enabled_ui(tool.uses_brush_size(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Size");
+            changed |= ui
+                .add(egui::Slider::new(brush_size, 1..=MAX_BRUSH_SIZE))
+                .changed();
+        });
+    });
+    changed
+}
+
+fn symmetry_picker(ui: &mut egui::Ui, symmetry: &mut Option<Symmetry>) {
+    let label = |symmetry: Option<Symmetry>| match symmetry {
+        None => "Off",
+        Some(Symmetry::Horizontal) => "Left-Right",
+        Some(Symmetry::Vertical) => "Top-Bottom",
+        Some(Symmetry::Point) => "Rotate 180°",
+    };
+    ui.horizontal(|ui| {
+        ui.label("Mirror");
+        egui::ComboBox::from_id_salt("symmetry")
+            .selected_text(label(*symmetry))
+            .show_ui(ui, |ui| {
+                let options = [
+                    None,
+                    Some(Symmetry::Horizontal),
+                    Some(Symmetry::Vertical),
+                    Some(Symmetry::Point),
+                ];
+                for option in options {
+                    ui.selectable_value(symmetry, option, label(option));
+                }
+            });
+    });
+}
+
+// 3x3 buttons pointing at the side or corner the map content keeps to
+fn anchor_picker(ui: &mut egui::Ui, anchor: &mut Anchor) {
+    let arrows = ["↖", "↑", "↗", "←", "•", "→", "↙", "↓", "↘"];
+    egui::Grid::new("resize_anchor").show(ui, |ui| {
+        for (i, (candidate, arrow)) in Anchor::ALL.into_iter().zip(arrows).enumerate() {
+            ui.selectable_value(anchor, candidate, arrow);
+            if i % 3 == 2 {
+                ui.end_row();
+            }
+        }
+    });
+}
+
+fn buildings_palette(ui: &mut egui::Ui, brush: &mut Brush, owner: &mut Option<PlayerId>) {
+    for kind in BuildingKind::ALL {
+        let selected = *brush == Brush::Building(Some(kind));
+        if ui.selectable_label(selected, kind.name()).clicked() {
+            *brush = Brush::Building(Some(kind));
+        }
+    }
+    let selected = *brush == Brush::Building(None);
+    if ui.selectable_label(selected, "Remove Building").clicked() {
+        *brush = Brush::Building(None);
+    }
+
+    ui.add_space(4.0);
+    ui.label("Owner");
+    egui::ComboBox::from_id_salt("building_owner")
+        .selected_text(owner_label(*owner))
+        .show_ui(ui, |ui| {
+            let owners = std::iter::once(None).chain((1..=MAX_BUILDING_OWNERS).map(|id| Some(PlayerId(id))));
+            for candidate in owners {
+                ui.selectable_value(owner, candidate, owner_label(candidate));
+            }
+        });
 }
 
+fn owner_label(owner: Option<PlayerId>) -> egui::RichText {
+    let color = owner_color(owner);
+    let text = match owner {
+        Some(PlayerId(id)) => format!("Player {}", id),
+        None => "Neutral".to_owned(),
+    };
+    egui::RichText::new(text).color(egui::Color32::from_rgb(
+        (color.red * 255.0) as u8,
+        (color.green * 255.0) as u8,
+        (color.blue * 255.0) as u8,
+    ))
+}
@@ -0,0 +1,42 @@
+use battleisles_domain::unit::{CatalogueError, UnitCatalogue};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use std::io;
+
+// Relative to the assets folder, so it is found wherever the game is started from and on wasm
+pub const UNIT_CATALOGUE_PATH: &str = "data/units.ron";
+
+#[derive(Asset, TypePath, Debug)]
+pub struct UnitCatalogueAsset(pub UnitCatalogue);
+
+// Handle to the catalogue being loaded, the game starts once it is ready
+#[derive(Resource)]
+pub struct UnitCatalogueHandle(pub Handle<UnitCatalogueAsset>);
+
+#[derive(Default)]
+pub struct UnitCatalogueLoader;
+
+impl AssetLoader for UnitCatalogueLoader {
+    type Asset = UnitCatalogueAsset;
+    type Settings = ();
+    type Error = CatalogueError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<UnitCatalogueAsset, CatalogueError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let text =
+            String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(UnitCatalogueAsset(UnitCatalogue::from_ron_str(&text)?))
+    }
+
+    // Plain .ron files are not claimed by extension, the catalogue is loaded by type
+    fn extensions(&self) -> &[&str] {
+        &[]
+    }
+}
@@ -1,16 +1,53 @@
+use battleisles_bevy::camera_controller::CameraControllerPlugin;
 use battleisles_bevy::map_model_plugin::MapModelPlugin;
-use battleisles_domain::map::Map;
+use battleisles_domain::game_state::{GameEvent, GameState};
+use battleisles_domain::building::{Building, BuildingKind};
+use battleisles_domain::map::{Hex, Map, Terrain};
+use battleisles_domain::player::{Faction, Player, PlayerId};
+use battleisles_domain::unit::UnitTypeId;
+use bevy::asset::LoadState;
 use bevy::prelude::*;
 use bevy::window::WindowMode;
 use bevy_egui::EguiPlugin;
+use catalogue::{
+    UnitCatalogueAsset, UnitCatalogueHandle, UnitCatalogueLoader, UNIT_CATALOGUE_PATH,
+};
 
+mod catalogue;
 mod ui;
+mod units;
+
+// The domain game state driving the app
+#[derive(Resource)]
+pub struct Game(pub GameState);
+
+#[derive(Event)]
+pub struct EndPhaseEvent;
+
+#[derive(Event)]
+pub struct EndTurnEvent;
+
+// Sent by the production dialog to build a unit in the current player's building at `at`
+#[derive(Event, Clone, Debug)]
+pub struct ProduceUnitEvent {
+    pub at: Hex,
+    pub unit_type: UnitTypeId,
+}
+
+// Forwards the domain events produced by game state transitions
+#[derive(Event, Clone, Debug)]
+pub struct GameStateEvent(pub GameEvent);
 
 pub struct BattleIslesGame;
 
 impl BattleIslesGame {
     pub fn run() {
         App::new()
+            .init_resource::<ui::UiState>()
+            .add_event::<EndPhaseEvent>()
+            .add_event::<EndTurnEvent>()
+            .add_event::<ProduceUnitEvent>()
+            .add_event::<GameStateEvent>()
             .add_plugins(DefaultPlugins.set(WindowPlugin {
                 primary_window: Some(Window {
                     mode: WindowMode::Windowed,
@@ -25,24 +62,121 @@ impl BattleIslesGame {
             .add_plugins(EguiPlugin {
                 enable_multipass_for_primary_context: false,
             })
-            .add_plugins(MapModelPlugin)
+            .add_plugins((MapModelPlugin, CameraControllerPlugin))
+            .init_asset::<UnitCatalogueAsset>()
+            .init_asset_loader::<UnitCatalogueLoader>()
             .add_systems(Startup, setup)
-            .add_systems(Update, ui::ui_system)
+            .add_systems(Update, start_game.run_if(not(resource_exists::<Game>)))
+            .add_systems(
+                Update,
+                (
+                    ui::ui_system,
+                    handle_turn_events,
+                    handle_produce_unit_events,
+                    ui::game_log_system,
+                    units::sync_unit_markers,
+                    units::update_fog_of_war,
+                )
+                    .chain()
+                    .run_if(resource_exists::<Game>),
+            )
             .run();
     }
 }
 
-pub fn setup(
+// The game itself starts in start_game once the unit catalogue is loaded
+pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(UnitCatalogueHandle(asset_server.load(UNIT_CATALOGUE_PATH)));
+}
+
+// A game without unit types cannot be played, so failing to load them ends the app
+fn start_game(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    handle: Res<UnitCatalogueHandle>,
+    mut catalogues: ResMut<Assets<UnitCatalogueAsset>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if let Some(LoadState::Failed(e)) = asset_server.get_load_state(&handle.0) {
+        println!("Failed to load unit catalogue {}: {}", UNIT_CATALOGUE_PATH, e);
+        exit.write(AppExit::error());
+        return;
+    }
+    let Some(UnitCatalogueAsset(catalogue)) = catalogues.remove(&handle.0) else {
+        return;
+    };
+    let map = starting_map();
+    commands.insert_resource(Game(GameState::new(
+        map.clone(),
+        vec![
+            Player::new(PlayerId(1), "Player 1", Faction::Drull),
+            Player::new(PlayerId(2), "Player 2", Faction::Kai),
+        ],
+        catalogue,
+    )));
+    commands.insert_resource(units::UnitMarkerAssets::new(&mut meshes));
+    MapModelPlugin::initialize_map_model(map, &mut commands, &mut meshes, &mut materials)
+        .expect("Failed to initialize map model");
+}
+
+fn handle_turn_events(
+    mut end_phase_events: EventReader<EndPhaseEvent>,
+    mut end_turn_events: EventReader<EndTurnEvent>,
+    mut game: ResMut<Game>,
+    mut game_events: EventWriter<GameStateEvent>,
 ) {
-    // Initialize any resources or entities needed for the editor
-    MapModelPlugin::initialize_map_model(
-        Map::new(5, 5),
-        &mut commands,
-        &mut meshes,
-        &mut materials,
-    )
-    .expect("Failed to initialize map model");
+    for _ in end_phase_events.read() {
+        game_events.write_batch(game.0.end_phase().into_iter().map(GameStateEvent));
+    }
+    for _ in end_turn_events.read() {
+        game_events.write_batch(game.0.end_turn().into_iter().map(GameStateEvent));
+    }
+}
+
+fn handle_produce_unit_events(
+    mut produce_events: EventReader<ProduceUnitEvent>,
+    mut game: ResMut<Game>,
+    mut ui_state: ResMut<ui::UiState>,
+    mut game_events: EventWriter<GameStateEvent>,
+) {
+    for ProduceUnitEvent { at, unit_type } in produce_events.read() {
+        match game.0.produce_unit(*at, unit_type) {
+            Ok(events) => {
+                game_events.write_batch(events.into_iter().map(GameStateEvent));
+            }
+            Err(e) => ui_state.log.push(format!("Cannot produce {}: {}", unit_type, e)),
+        }
+    }
+}
+
+// Small island with a headquarters and factory for each player
+fn starting_map() -> Map {
+    let mut map = Map::new(5, 5);
+    let land = [(0, 0), (1, 0), (0, 1), (1, 1), (3, 3), (3, 4), (4, 4)];
+    for (col, row) in land {
+        set_tile(&mut map, col, row, Terrain::Plains, None);
+    }
+    let buildings = [
+        (0, 0, BuildingKind::Headquarters, 1),
+        (1, 0, BuildingKind::Factory, 1),
+        (4, 4, BuildingKind::Headquarters, 2),
+        (3, 4, BuildingKind::Factory, 2),
+    ];
+    for (col, row, kind, owner) in buildings {
+        let building = Building::new(kind, Some(PlayerId(owner)));
+        set_tile(&mut map, col, row, Terrain::Plains, Some(building));
+    }
+    map
+}
+
+fn set_tile(map: &mut Map, col: i32, row: i32, terrain: Terrain, building: Option<Building>) {
+    let Some(hex) = map.tile_at_offset(col, row).map(|tile| tile.position()) else {
+        return;
+    };
+    if let Some(tile) = map.tile_at_mut(hex) {
+        tile.terrain = terrain;
+        tile.building = building;
+    }
 }
@@ -1,34 +1,185 @@
+use crate::{EndPhaseEvent, EndTurnEvent, Game, GameStateEvent, ProduceUnitEvent};
+use battleisles_domain::game_state::{GameEvent, GameState, Phase};
+use battleisles_domain::map::Hex;
+use battleisles_domain::unit::UnitId;
+use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
 
-pub fn ui_system(mut contexts: EguiContexts) {
+const MAX_LOG_LINES: usize = 50;
+
+#[derive(Resource, Default)]
+pub struct UiState {
+    pub log: Vec<String>,
+    pub production_open: bool,
+    // Building the production dialog builds in, reset when it is no longer the current player's
+    pub production_building: Option<Hex>,
+}
+
+pub fn game_log_system(
+    mut events: EventReader<GameStateEvent>,
+    game: Res<Game>,
+    mut ui_state: ResMut<UiState>,
+) {
+    for GameStateEvent(event) in events.read() {
+        let line = match event {
+            GameEvent::TurnStarted { player, turn } => {
+                format!("Turn {}: {}", turn, player_name(&game, *player))
+            }
+            GameEvent::PhaseStarted { player, phase } => {
+                format!("{} - {}", player_name(&game, *player), phase_name(*phase))
+            }
+            GameEvent::BuildingCaptured {
+                kind,
+                player,
+                previous_owner,
+                ..
+            } => match previous_owner {
+                Some(previous) => format!(
+                    "{} captured a {} from {}",
+                    player_name(&game, *player),
+                    kind.name(),
+                    player_name(&game, *previous)
+                ),
+                None => format!("{} captured a {}", player_name(&game, *player), kind.name()),
+            },
+            GameEvent::EnergyCollected {
+                player,
+                amount,
+                total,
+            } => format!(
+                "{} collected {} energy ({} total)",
+                player_name(&game, *player),
+                amount,
+                total
+            ),
+            GameEvent::UnitProduced { unit, player, .. } => format!(
+                "{} produced a {}",
+                player_name(&game, *player),
+                unit_name(&game, *unit)
+            ),
+            GameEvent::UnitServiced {
+                unit,
+                repaired,
+                resupplied,
+            } => {
+                let name = unit_name(&game, *unit);
+                match (*repaired, *resupplied) {
+                    (0, _) => format!("{} resupplied", name),
+                    (health, false) => format!("{} repaired by {}", name, health),
+                    (health, true) => format!("{} repaired by {} and resupplied", name, health),
+                }
+            }
+        };
+        ui_state.log.push(line);
+    }
+    if ui_state.log.len() > MAX_LOG_LINES {
+        let excess = ui_state.log.len() - MAX_LOG_LINES;
+        ui_state.log.drain(..excess);
+    }
+}
+
+pub fn ui_system(
+    mut contexts: EguiContexts,
+    game: Res<Game>,
+    mut ui_state: ResMut<UiState>,
+    mut end_phase_events: EventWriter<EndPhaseEvent>,
+    mut end_turn_events: EventWriter<EndTurnEvent>,
+    mut produce_events: EventWriter<ProduceUnitEvent>,
+) {
     let ctx = contexts.ctx_mut();
+    let state = &game.0;
+    let current = state.current_player();
 
-    // Top panel
+    // Top panel: turn and phase
     egui::TopBottomPanel::top("top_panel")
         .default_height(50.0)
         .show(ctx, |ui| {
-            ui.add(egui::Label::new("Top Panel"));
+            ui.horizontal(|ui| {
+                ui.label(format!("Turn {}", state.turn()));
+                ui.separator();
+                ui.label(format!("{} ({:?})", current.name, current.faction));
+                ui.separator();
+                ui.label(phase_name(state.phase()));
+                ui.separator();
+                ui.label(format!(
+                    "Energy {} (+{})",
+                    current.energy,
+                    state.income(current.id)
+                ));
+                ui.separator();
+                if ui
+                    .selectable_label(ui_state.production_open, "Production")
+                    .clicked()
+                {
+                    ui_state.production_open = !ui_state.production_open;
+                }
+                let end_phase_label = match state.phase() {
+                    Phase::Movement => "End Movement",
+                    Phase::Attack => "End Attack",
+                };
+                if ui.button(end_phase_label).clicked() {
+                    end_phase_events.write(EndPhaseEvent);
+                }
+                if ui.button("End Turn").clicked() {
+                    end_turn_events.write(EndTurnEvent);
+                }
+            });
         });
 
-    // Bottom panel
+    if ui_state.production_open {
+        let mut open = true;
+        egui::Window::new("Production")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                production_dialog(ui, state, &mut ui_state, &mut produce_events);
+            });
+        ui_state.production_open = open;
+    }
+
+    // Bottom panel: most recent game event
     egui::TopBottomPanel::bottom("bottom_panel")
         .default_height(50.0)
         .show(ctx, |ui| {
-            ui.add(egui::Label::new("Bottom Panel"));
+            ui.add(egui::Label::new(
+                ui_state.log.last().map(String::as_str).unwrap_or_default(),
+            ));
         });
 
-    // Left panel
+    // Left panel: players
     egui::SidePanel::left("left_panel")
         .default_width(100.0)
         .show(ctx, |ui| {
-            ui.add(egui::Label::new("Left Panel"));
+            ui.heading("Players");
+            ui.separator();
+            for player in state.players() {
+                let text = format!(
+                    "{} - {} units, {} buildings",
+                    player.name,
+                    state.units_of(player.id).count(),
+                    state.buildings_of(player.id).count()
+                );
+                if player.id == current.id {
+                    ui.strong(text);
+                } else {
+                    ui.label(text);
+                }
+            }
         });
 
-    // Right panel
+    // Right panel: event log
     egui::SidePanel::right("right_panel")
         .default_width(100.0)
         .show(ctx, |ui| {
-            ui.add(egui::Label::new("Right Panel"));
+            ui.heading("Log");
+            ui.separator();
+            egui::ScrollArea::vertical()
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for line in &ui_state.log {
+                        ui.label(line);
+                    }
+                });
         });
 
     // Set the background color of the panels to light blue
@@ -37,3 +188,109 @@ pub fn ui_system(mut contexts: EguiContexts) {
         ..Default::default()
     });
 }
+
+fn production_dialog(
+    ui: &mut egui::Ui,
+    state: &GameState,
+    ui_state: &mut UiState,
+    produce_events: &mut EventWriter<ProduceUnitEvent>,
+) {
+    let player = state.current_player().id;
+    let buildings = state
+        .buildings_of(player)
+        .filter(|(_, building)| {
+            state
+                .catalogue()
+                .iter()
+                .any(|unit_type| building.kind.produces(unit_type.movement_class))
+        })
+        .collect::<Vec<_>>();
+    if !buildings
+        .iter()
+        .any(|(hex, _)| Some(*hex) == ui_state.production_building)
+    {
+        ui_state.production_building = buildings.first().map(|(hex, _)| *hex);
+    }
+    let Some(selected) = ui_state.production_building else {
+        ui.label("You own no buildings that can produce units.");
+        return;
+    };
+
+    let building_label = |hex: Hex| {
+        let kind = state
+            .map
+            .tile_at(hex)
+            .and_then(|tile| tile.building)
+            .map(|b| b.kind);
+        format!(
+            "{} at ({}, {})",
+            kind.map(|kind| kind.name()).unwrap_or_default(),
+            hex.x,
+            hex.y
+        )
+    };
+    egui::ComboBox::from_label("Building")
+        .selected_text(building_label(selected))
+        .show_ui(ui, |ui| {
+            for (hex, _) in &buildings {
+                ui.selectable_value(
+                    &mut ui_state.production_building,
+                    Some(*hex),
+                    building_label(*hex),
+                );
+            }
+        });
+    ui.separator();
+
+    let Some(building) = state.map.tile_at(selected).and_then(|tile| tile.building) else {
+        return;
+    };
+    egui::Grid::new("production_grid")
+        .striped(true)
+        .show(ui, |ui| {
+            for unit_type in state
+                .catalogue()
+                .iter()
+                .filter(|unit_type| building.kind.produces(unit_type.movement_class))
+            {
+                ui.label(&unit_type.name);
+                ui.label(format!("{} energy", unit_type.cost));
+                let check = state.check_production(selected, &unit_type.id);
+                let button = ui.add_enabled(check.is_ok(), egui::Button::new("Build"));
+                let button = match &check {
+                    Err(e) => button.on_disabled_hover_text(e.to_string()),
+                    Ok(_) => button,
+                };
+                if button.clicked() {
+                    produce_events.write(ProduceUnitEvent {
+                        at: selected,
+                        unit_type: unit_type.id.clone(),
+                    });
+                }
+                ui.end_row();
+            }
+        });
+}
+
+fn player_name(game: &Game, id: battleisles_domain::player::PlayerId) -> &str {
+    game.0
+        .player(id)
+        .map(|player| player.name.as_str())
+        .unwrap_or("Unknown player")
+}
+
+fn unit_name(game: &Game, id: UnitId) -> String {
+    let state = &game.0;
+    state
+        .unit(id)
+        .and_then(|unit| state.catalogue().get(&unit.unit_type))
+        .map(|unit_type| unit_type.name.clone())
+        .unwrap_or_else(|| format!("Unit {}", id.0))
+}
+
+fn phase_name(phase: Phase) -> &'static str {
+    match phase {
+        Phase::Movement => "Movement phase",
+        Phase::Attack => "Attack phase",
+    }
+}
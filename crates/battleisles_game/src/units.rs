@@ -0,0 +1,113 @@
+use crate::Game;
+use battleisles_bevy::building_materials::owner_color;
+use battleisles_bevy::map_model::MapModel;
+use battleisles_bevy::map_model_plugin::{ApplyFogOfWar, MapUnit};
+use battleisles_domain::player::PlayerId;
+use battleisles_domain::unit::UnitId;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+// Lift of the unit marker spheres above the surface of their tile
+const UNIT_LIFT: Vec3 = Vec3::new(0.0, 0.0, 0.3);
+
+#[derive(Component, Clone, Copy)]
+pub struct UnitMarker(pub UnitId);
+
+// Shared mesh and owner-coloured materials for the unit markers
+#[derive(Resource)]
+pub struct UnitMarkerAssets {
+    mesh: Handle<Mesh>,
+    materials: HashMap<PlayerId, Handle<StandardMaterial>>,
+}
+
+impl UnitMarkerAssets {
+    pub fn new(meshes: &mut Assets<Mesh>) -> Self {
+        UnitMarkerAssets {
+            mesh: meshes.add(Sphere::new(0.3)),
+            materials: HashMap::new(),
+        }
+    }
+
+    fn material(
+        &mut self,
+        owner: PlayerId,
+        materials: &mut Assets<StandardMaterial>,
+    ) -> Handle<StandardMaterial> {
+        self.materials
+            .entry(owner)
+            .or_insert_with(|| {
+                materials.add(StandardMaterial::from_color(owner_color(Some(owner))))
+            })
+            .clone()
+    }
+}
+
+// Keeps one marker entity per unit in the game state
+pub fn sync_unit_markers(
+    game: Res<Game>,
+    map_model: Option<Res<MapModel>>,
+    mut markers: Query<(Entity, &UnitMarker, &mut MapUnit, &mut Transform)>,
+    mut assets: ResMut<UnitMarkerAssets>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    let Some(map_model) = map_model else {
+        return;
+    };
+    if !game.is_changed() && !map_model.is_changed() {
+        return;
+    }
+    let state = &game.0;
+
+    let mut marked = Vec::new();
+    for (entity, UnitMarker(id), mut map_unit, mut transform) in markers.iter_mut() {
+        let Some(unit) = state.unit(*id) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        let Some(pos) = map_model.tile_surface(unit.position) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        map_unit.position = unit.position;
+        transform.translation = pos + UNIT_LIFT;
+        marked.push(*id);
+    }
+
+    for unit in state
+        .units()
+        .iter()
+        .filter(|unit| !marked.contains(&unit.id))
+    {
+        let Some(pos) = map_model.tile_surface(unit.position) else {
+            continue;
+        };
+        commands.spawn((
+            Mesh3d(assets.mesh.clone()),
+            MeshMaterial3d(assets.material(unit.owner, &mut materials)),
+            Transform::from_translation(pos + UNIT_LIFT),
+            MapUnit {
+                position: unit.position,
+                owner: unit.owner,
+            },
+            UnitMarker(unit.id),
+        ));
+    }
+}
+
+// Players share the screen, so the map is always shown as the current player knows it
+pub fn update_fog_of_war(game: Res<Game>, mut fog_events: EventWriter<ApplyFogOfWar>) {
+    if !game.is_changed() {
+        return;
+    }
+    let state = &game.0;
+    let viewer = state.current_player().id;
+    let tiles = state
+        .visibility(viewer)
+        .map(|visibility| visibility.tiles(&state.map))
+        .unwrap_or_default();
+    fog_events.write(ApplyFogOfWar {
+        viewer: Some(viewer),
+        tiles,
+    });
+}